@@ -3,7 +3,19 @@ use std::collections::HashMap;
 use types_reader::TokensObject;
 
 pub struct FnOverride<'s> {
-    pub retries: usize,
+    /// `None` - retries of the proto option or of the client by idempotency level
+    pub retries: Option<usize>,
+    pub resumable: bool,
+    pub subscription: bool,
+    pub stream_idle_timeout_sec: Option<u64>,
+    pub stream_deadline_sec: Option<u64>,
+    pub cache_ttl_ms: Option<u64>,
+    pub cache_max_entries: Option<usize>,
+    pub cache_stale_ms: Option<u64>,
+    pub hedge_delay_ms: Option<u64>,
+    pub max_requests_per_second: Option<u32>,
+    pub max_in_flight: Option<usize>,
+    pub limit_queue_timeout_ms: Option<u64>,
     pub token_stream: &'s TokensObject,
 }
 
@@ -22,11 +34,94 @@ impl<'s> FnOverride<'s> {
         for item in overrides.iter() {
             let name: String = item.get_named_param("fn_name")?.try_into()?;
 
-            let retries: usize = item.get_named_param("retries")?.try_into()?;
+            let retries: Option<usize> = match item.try_get_named_param("retries") {
+                Some(value) => Some(value.try_into()?),
+                None => None,
+            };
+
+            let resumable = match item.try_get_named_param("resumable") {
+                Some(resumable) => resumable
+                    .unwrap_as_value()?
+                    .unwrap_value()?
+                    .as_bool()?
+                    .get_value(),
+                None => false,
+            };
+
+            let subscription = match item.try_get_named_param("subscription") {
+                Some(subscription) => subscription
+                    .unwrap_as_value()?
+                    .unwrap_value()?
+                    .as_bool()?
+                    .get_value(),
+                None => false,
+            };
+
+            let stream_idle_timeout_sec: Option<u64> =
+                match item.try_get_named_param("stream_idle_timeout_sec") {
+                    Some(value) => Some(value.try_into()?),
+                    None => None,
+                };
+
+            let stream_deadline_sec: Option<u64> =
+                match item.try_get_named_param("stream_deadline_sec") {
+                    Some(value) => Some(value.try_into()?),
+                    None => None,
+                };
+
+            let cache_ttl_ms: Option<u64> = match item.try_get_named_param("cache_ttl_ms") {
+                Some(value) => Some(value.try_into()?),
+                None => None,
+            };
+
+            let cache_max_entries: Option<usize> =
+                match item.try_get_named_param("cache_max_entries") {
+                    Some(value) => Some(value.try_into()?),
+                    None => None,
+                };
+
+            let cache_stale_ms: Option<u64> = match item.try_get_named_param("cache_stale_ms") {
+                Some(value) => Some(value.try_into()?),
+                None => None,
+            };
+
+            let hedge_delay_ms: Option<u64> = match item.try_get_named_param("hedge_delay_ms") {
+                Some(value) => Some(value.try_into()?),
+                None => None,
+            };
+
+            let max_requests_per_second: Option<u32> =
+                match item.try_get_named_param("max_requests_per_second") {
+                    Some(value) => Some(value.try_into()?),
+                    None => None,
+                };
+
+            let max_in_flight: Option<usize> = match item.try_get_named_param("max_in_flight") {
+                Some(value) => Some(value.try_into()?),
+                None => None,
+            };
+
+            let limit_queue_timeout_ms: Option<u64> =
+                match item.try_get_named_param("limit_queue_timeout_ms") {
+                    Some(value) => Some(value.try_into()?),
+                    None => None,
+                };
+
             result.insert(
                 name,
                 FnOverride {
                     retries,
+                    resumable,
+                    subscription,
+                    stream_idle_timeout_sec,
+                    stream_deadline_sec,
+                    cache_ttl_ms,
+                    cache_max_entries,
+                    cache_stale_ms,
+                    hedge_delay_ms,
+                    max_requests_per_second,
+                    max_in_flight,
+                    limit_queue_timeout_ms,
                     token_stream: item,
                 },
             );
@@ -29,10 +29,10 @@ pub fn generate(
 
 
 
-    let proto_file:String = params_list.get_named_param("proto_file")?.try_into()?;
+    let proto_file_name:String = params_list.get_named_param("proto_file")?.try_into()?;
 
 
-    let proto_file = ProtoServiceDescription::read_proto_file(&proto_file);
+    let proto_file = ProtoServiceDescription::read_proto_file(&proto_file_name);
 
     let grpc_service_name = &proto_file.service_name;
     let grpc_service_name_token = proc_macro2::TokenStream::from_str(format!("{}Client",proto_file.get_service_name().as_str()).as_str()).unwrap() ;
@@ -47,9 +47,87 @@ pub fn generate(
     let timeout_sec:u64 = params_list.get_named_param("request_timeout_sec")?.try_into()?;
     let retries:usize = params_list.get_named_param("retries")?.try_into()?;
 
+    let stream_max_items:Option<usize> = match params_list.try_get_named_param("stream_max_items") {
+        Some(value) => Some(value.try_into()?),
+        None => None,
+    };
+
+    let stream_max_bytes:Option<usize> = match params_list.try_get_named_param("stream_max_bytes") {
+        Some(value) => Some(value.try_into()?),
+        None => None,
+    };
+
+    let with_stream_read_limits = if stream_max_items.is_some() || stream_max_bytes.is_some() {
+        let with_max_items = stream_max_items.map(|value| quote::quote!(.with_max_items(#value)));
+        let with_max_bytes = stream_max_bytes.map(|value| quote::quote!(.with_max_bytes(#value)));
+        quote::quote!(.with_stream_read_limits(my_grpc_extensions::StreamReadLimits::new() #with_max_items #with_max_bytes))
+    } else {
+        quote::quote!()
+    };
+
+    let max_requests_per_second:Option<u32> = match params_list.try_get_named_param("max_requests_per_second") {
+        Some(value) => Some(value.try_into()?),
+        None => None,
+    };
+
+    let max_in_flight:Option<usize> = match params_list.try_get_named_param("max_in_flight") {
+        Some(value) => Some(value.try_into()?),
+        None => None,
+    };
+
+    let limit_queue_timeout_ms:Option<u64> = match params_list.try_get_named_param("limit_queue_timeout_ms") {
+        Some(value) => Some(value.try_into()?),
+        None => None,
+    };
+
+    let with_limits = match super::generate_client_limits(max_requests_per_second, max_in_flight, limit_queue_timeout_ms) {
+        Some(limits) => quote::quote!(.with_limits(#limits)),
+        None => quote::quote!(),
+    };
+
+    let with_max_retry_delay = match params_list.try_get_named_param("max_retry_delay_ms") {
+        Some(value) => {
+            let max_retry_delay_ms:u64 = value.try_into()?;
+            quote::quote!(.with_max_retry_delay(std::time::Duration::from_millis(#max_retry_delay_ms)))
+        }
+        None => quote::quote!(),
+    };
+
+    let retry_non_idempotent = match params_list.try_get_named_param("retry_non_idempotent") {
+        Some(value) => value.unwrap_as_value()?.unwrap_value()?.as_bool()?.get_value(),
+        None => false,
+    };
+
 
     
-    let crate_ns:String = params_list.get_named_param("crate_ns")?.try_into()?;
+    let generate_proto = match params_list.try_get_named_param("generate_proto") {
+        Some(generate_proto) => Some((generate_proto, generate_proto.unwrap_as_value()?.unwrap_value()?.as_bool()?.get_value())),
+        None => None,
+    };
+
+    let (crate_ns, proto_module) = match generate_proto {
+        Some((generate_proto, true)) => {
+            let module_name = match params_list.try_get_named_param("proto_module") {
+                Some(proto_module) => proto_module.unwrap_any_value_as_str()?.as_str()?.to_string(),
+                None => proto_file.get_service_name().as_snake_case(),
+            };
+
+            let proto_module = my_grpc_extensions_build::ProtoCodegen::new(&proto_file_name)
+                .build_client(true)
+                .generate_module(&module_name)
+                .map_err(|err| generate_proto.throw_error_at_value_token(err.as_str()))?;
+
+            let proto_module = proc_macro2::TokenStream::from_str(proto_module.as_str())
+                .map_err(|err| generate_proto.throw_error_at_value_token(format!("Can not parse generated code. Err: {}", err).as_str()))?;
+
+            (format!("self::{}", module_name), proto_module)
+        }
+        _ => {
+            let crate_ns:String = params_list.get_named_param("crate_ns")?.try_into()?;
+            (crate_ns, quote::quote!())
+        }
+    };
+
     let mut use_name_spaces = Vec::new();
     use_name_spaces.push(proc_macro2::TokenStream::from_str(format!("use {}::*", crate_ns).as_str()).unwrap());
 
@@ -68,9 +146,65 @@ pub fn generate(
             let message = format!("Method {} is not found in proto file for service {}", override_fn_name, grpc_service_name);
             return Err(fn_override.token_stream.throw_error_at_value_token(message.as_str()));
         }
+
+        if fn_override.resumable && !proto_file.get_rpc(override_fn_name).map(|rpc| rpc.is_server_streaming()).unwrap_or(false){
+            let message = format!("Method {} can not be resumable. Only rpc with single request and stream response can be", override_fn_name);
+            return Err(fn_override.token_stream.throw_error_at_value_token(message.as_str()));
+        }
+
+        if fn_override.subscription && !proto_file.get_rpc(override_fn_name).map(|rpc| rpc.is_server_streaming()).unwrap_or(false){
+            let message = format!("Method {} can not be subscription. Only rpc with single request and stream response can be", override_fn_name);
+            return Err(fn_override.token_stream.throw_error_at_value_token(message.as_str()));
+        }
+
+        let has_stream_timeouts = fn_override.stream_idle_timeout_sec.is_some() || fn_override.stream_deadline_sec.is_some();
+        if has_stream_timeouts && !proto_file.get_rpc(override_fn_name).and_then(|rpc| rpc.get_output_param()).map(|output| output.is_stream()).unwrap_or(false){
+            let message = format!("Method {} does not return stream. stream_idle_timeout_sec and stream_deadline_sec can not be applied", override_fn_name);
+            return Err(fn_override.token_stream.throw_error_at_value_token(message.as_str()));
+        }
+
+        let has_cache_params = fn_override.cache_max_entries.is_some() || fn_override.cache_stale_ms.is_some();
+        if has_cache_params && fn_override.cache_ttl_ms.is_none(){
+            let message = format!("Method {} has cache_max_entries or cache_stale_ms without cache_ttl_ms", override_fn_name);
+            return Err(fn_override.token_stream.throw_error_at_value_token(message.as_str()));
+        }
+
+        if fn_override.cache_ttl_ms.is_some() && !proto_file.get_rpc(override_fn_name).map(|rpc| rpc.is_unary()).unwrap_or(false){
+            let message = format!("Method {} can not be cached. Only rpc with single request and single response can be", override_fn_name);
+            return Err(fn_override.token_stream.throw_error_at_value_token(message.as_str()));
+        }
+
+        if fn_override.limit_queue_timeout_ms.is_some() && fn_override.max_requests_per_second.is_none() && fn_override.max_in_flight.is_none(){
+            let message = format!("Method {} has limit_queue_timeout_ms without max_requests_per_second or max_in_flight", override_fn_name);
+            return Err(fn_override.token_stream.throw_error_at_value_token(message.as_str()));
+        }
+
+        if fn_override.hedge_delay_ms.is_some() {
+            let rpc = proto_file.get_rpc(override_fn_name);
+
+            if !rpc.map(|rpc| rpc.is_unary()).unwrap_or(false){
+                let message = format!("Method {} can not be hedged. Only rpc with single request and single response can be", override_fn_name);
+                return Err(fn_override.token_stream.throw_error_at_value_token(message.as_str()));
+            }
+
+            if !rpc.map(|rpc| rpc.get_idempotency_level().is_safe_to_retry()).unwrap_or(false){
+                let message = format!("Method {} can not be hedged. Only rpc with idempotency_level NO_SIDE_EFFECTS or IDEMPOTENT can be", override_fn_name);
+                return Err(fn_override.token_stream.throw_error_at_value_token(message.as_str()));
+            }
+        }
     }
     
-    let grpc_methods = super::generate_grpc_methods(&proto_file, retries, &overrides, with_telemetry);
+    let grpc_methods = super::generate_grpc_methods(&proto_file, params_list.get_named_param("proto_file")?, retries, retry_non_idempotent, &overrides, with_telemetry)?;
+
+    let (response_cache_fields, response_cache_init) = super::generate_response_cache_fields(&proto_file, &overrides);
+
+    let mut with_method_limits = Vec::new();
+
+    for (override_fn_name, fn_override) in &overrides{
+        if let Some(limits) = super::generate_client_limits(fn_override.max_requests_per_second, fn_override.max_in_flight, fn_override.limit_queue_timeout_ms){
+            with_method_limits.push(quote::quote!(.with_method_limits(#override_fn_name, #limits)));
+        }
+    }
 
 
     let fn_create_service = if with_telemetry{
@@ -128,10 +262,14 @@ pub fn generate(
         (quote::quote!(), quote::quote!())
     };
 
+    let service_docs = proto_file.comments.iter();
+
     let grpc_service_factory_name = proc_macro2::TokenStream::from_str(format!("{}GrpcServiceFactory", struct_name.to_string()).as_str()).unwrap() ;
 
     Ok(quote::quote! {
 
+        #proto_module
+
         #(#use_name_spaces;)*
 
         type TGrpcService = #t_grpc_service;
@@ -162,9 +300,11 @@ pub fn generate(
         }
       }
 
+      #(#[doc = #service_docs])*
       pub struct #struct_name{
         channel: my_grpc_extensions::GrpcChannelPool<TGrpcService>,
-        settings: std::sync::Arc<dyn my_grpc_extensions::GrpcClientSettings + Send + Sync + 'static>
+        settings: std::sync::Arc<dyn my_grpc_extensions::GrpcClientSettings + Send + Sync + 'static>,
+        #(#response_cache_fields,)*
       }
 
       impl #struct_name{
@@ -178,7 +318,8 @@ pub fn generate(
                     std::time::Duration::from_secs(#ping_timeout_sec),
                     std::time::Duration::from_secs(#ping_interval_sec),
                     
-                ),
+                )#with_stream_read_limits #with_max_retry_delay #with_limits #(#with_method_limits)*,
+                #(#response_cache_init,)*
             }
         }
 
@@ -186,6 +327,16 @@ pub fn generate(
             #settings_service_name
         }
 
+        /// Limits of all requests of the client
+        pub fn set_limits(&self, limits: my_grpc_extensions::GrpcClientLimits) {
+            self.channel.set_limits(limits);
+        }
+
+        /// Limits of the rpc on top of the client limits. fn_name is the name of rpc in the proto file
+        pub fn set_method_limits(&self, fn_name: &str, limits: my_grpc_extensions::GrpcClientLimits) {
+            self.channel.set_method_limits(fn_name, limits);
+        }
+
         #ssh_impl
 
         #(#grpc_methods)*  
@@ -1,15 +1,19 @@
 use std::{collections::HashMap, str::FromStr};
 
-use proto_file_reader::{ParamType, ProtoServiceDescription};
+use proto_file_reader::{ParamType, ProtoRpc, ProtoServiceDescription};
+use types_reader::TokensObject;
 
 use super::fn_override::FnOverride;
 
+/// `proto_file_param` is the span of errors found in the proto file
 pub fn generate_grpc_methods(
     proto_file: &ProtoServiceDescription,
+    proto_file_param: &TokensObject,
     retries_amount: usize,
+    retry_non_idempotent: bool,
     overrides: &HashMap<String, FnOverride>,
     width_telemetry: bool,
-) -> Vec<proc_macro2::TokenStream> {
+) -> Result<Vec<proc_macro2::TokenStream>, syn::Error> {
     let mut result = Vec::new();
 
     for rpc in &proto_file.rpc {
@@ -24,15 +28,54 @@ pub fn generate_grpc_methods(
 
         let output_data_type = get_func_out_data_type(output_param.as_ref());
 
+        let resumable = match overrides.get(&rpc.name) {
+            Some(value) => value.resumable,
+            None => false,
+        };
+
         let request_fn_name = get_request_fn_name(input_param.as_ref());
-        let response_fn_name = get_response_fn_name(output_param.as_ref());
 
-        let retries_amount = if let Some(value) = overrides.get(&rpc.name) {
-            value.retries
+        let (output_data_type, response_fn_name) = if resumable {
+            let input_param =
+                proc_macro2::TokenStream::from_str(input_param.as_ref().unwrap().get_name())
+                    .unwrap();
+            let output_param =
+                proc_macro2::TokenStream::from_str(output_param.as_ref().unwrap().get_name())
+                    .unwrap();
+            (
+                quote::quote!(my_grpc_extensions::ResumableStreamedResponse<'_, TGrpcService, #input_param, #output_param, Self>),
+                quote::quote!(get_resumable_streamed_response(self).await?),
+            )
         } else {
+            (
+                output_data_type,
+                get_response_fn_name(output_param.as_ref()),
+            )
+        };
+
+        let fn_override = overrides.get(&rpc.name);
+
+        let retries_amount = if let Some(value) = fn_override.and_then(|value| value.retries) {
+            value
+        } else if let Some(value) = rpc.get_custom_option("retries") {
+            parse_option_value(proto_file_param, rpc, "retries", value)?
+        } else if retry_non_idempotent || rpc.get_idempotency_level().is_safe_to_retry() {
             retries_amount
+        } else {
+            0
         };
 
+        if resumable && retries_amount == 0 {
+            let fn_override = fn_override.unwrap();
+            let message = format!(
+                "Method {} can not be resumable with 0 retries. Retries are used as resume attempts",
+                rpc.name
+            );
+            return Err(fn_override
+                .token_stream
+                .throw_error_at_value_token(message.as_str()));
+        }
+
         let with_retries = if retries_amount > 0 {
             let amount = proc_macro2::Literal::usize_unsuffixed(retries_amount);
             quote::quote!(.with_retries(#amount))
@@ -46,36 +89,297 @@ pub fn generate_grpc_methods(
             quote::quote!()
         };
 
+        let rpc_name = rpc.name.as_str();
+
         let get_channel = if width_telemetry {
-            quote::quote!(self.channel.get_channel(ctx))
+            quote::quote!(self.channel.get_channel(ctx).with_method_limiter(self.channel.get_method_limiter(#rpc_name)))
+        } else {
+            quote::quote!(self.channel.get_channel().with_method_limiter(self.channel.get_method_limiter(#rpc_name)))
+        };
+
+        let with_request_timeout = match rpc.get_custom_option("request_timeout_ms") {
+            Some(value) => {
+                let timeout_ms: u64 =
+                    parse_option_value(proto_file_param, rpc, "request_timeout_ms", value)?;
+                quote::quote!(.with_request_timeout(std::time::Duration::from_millis(#timeout_ms)))
+            }
+            None => quote::quote!(),
+        };
+
+        let with_stream_timeouts = match overrides.get(&rpc.name) {
+            Some(value) => get_stream_timeouts(value),
+            None => quote::quote!(),
+        };
+
+        let with_hedge_delay = match overrides.get(&rpc.name) {
+            Some(value) => get_hedge_delay(value),
+            None => quote::quote!(),
+        };
+
+        let deprecated = if rpc.is_deprecated() {
+            quote::quote!(#[deprecated])
         } else {
-            quote::quote!(self.channel.get_channel())
+            quote::quote!()
         };
 
         //let log_fn_name = format!("{}::{}", struct_name, fn_name.to_string());
 
+        let docs = rpc.comments.iter();
+
+        let call = quote::quote! {
+            let channel = #get_channel #with_request_timeout #with_stream_timeouts #with_hedge_delay;
+
+            let result = channel
+                .#request_fn_name(input_data)
+                #with_retries
+                .#response_fn_name;
+
+            Ok(result)
+        };
+
+        let cached = match fn_override {
+            Some(value) => value.cache_ttl_ms.is_some(),
+            None => false,
+        };
+
+        if cached && !rpc.get_idempotency_level().is_safe_to_retry() {
+            let message = format!(
+                "Method {} can not be cached. Only rpc with idempotency_level = NO_SIDE_EFFECTS | IDEMPOTENT can be cached",
+                rpc.name
+            );
+            return Err(fn_override
+                .unwrap()
+                .token_stream
+                .throw_error_at_value_token(message.as_str()));
+        }
+
+        let body = if cached {
+            let cache_field = get_response_cache_field_name(rpc);
+
+            quote::quote! {
+                let cache_key = my_grpc_extensions::get_response_cache_key(&input_data);
+
+                self.#cache_field
+                    .get_or_fetch(cache_key, async move { #call })
+                    .await
+            }
+        } else {
+            call
+        };
+
         let item = quote::quote! {
+            #(#[doc = #docs])*
+            #deprecated
             pub async fn #fn_name(
                 &self,
                 input_data: #input_data_type,
                 #ctx_param
             ) -> Result<#output_data_type, my_grpc_extensions::GrpcReadError> {
-                let channel = #get_channel;
+                #body
+            }
+        };
 
-                let result = channel
-                    .#request_fn_name(input_data)
-                    #with_retries
-                    .#response_fn_name;
+        result.push(item);
 
-                Ok(result)
+        let is_bidi = input_param
+            .as_ref()
+            .map(|param| param.is_stream())
+            .unwrap_or(false)
+            && output_param
+                .as_ref()
+                .map(|param| param.is_stream())
+                .unwrap_or(false);
 
-            }
+        if is_bidi {
+            let open_fn_name = proc_macro2::TokenStream::from_str(
+                format!("open_{}", rpc.get_fn_name().as_snake_case()).as_str(),
+            )
+            .unwrap();
+
+            let input_param =
+                proc_macro2::TokenStream::from_str(input_param.as_ref().unwrap().get_name())
+                    .unwrap();
+            let output_param =
+                proc_macro2::TokenStream::from_str(output_param.as_ref().unwrap().get_name())
+                    .unwrap();
+
+            let docs = rpc.comments.iter();
+
+            result.push(quote::quote! {
+                #(#[doc = #docs])*
+                #deprecated
+                pub async fn #open_fn_name(
+                    &self,
+                    #ctx_param
+                ) -> Result<my_grpc_extensions::BidiStream<'_, #input_param, #output_param>, my_grpc_extensions::GrpcReadError> {
+                    let channel = #get_channel #with_request_timeout #with_stream_timeouts;
+
+                    Ok(channel.open_bidi_stream(self))
+                }
+            });
+        }
+
+        let subscription = match overrides.get(&rpc.name) {
+            Some(value) => value.subscription,
+            None => false,
         };
 
-        result.push(item);
+        if subscription {
+            let subscribe_fn_name = proc_macro2::TokenStream::from_str(
+                format!("subscribe_{}", rpc.get_fn_name().as_snake_case()).as_str(),
+            )
+            .unwrap();
+
+            let input_param =
+                proc_macro2::TokenStream::from_str(input_param.as_ref().unwrap().get_name())
+                    .unwrap();
+            let output_param =
+                proc_macro2::TokenStream::from_str(output_param.as_ref().unwrap().get_name())
+                    .unwrap();
+
+            let docs = rpc.comments.iter();
+
+            result.push(quote::quote! {
+                #(#[doc = #docs])*
+                #deprecated
+                pub fn #subscribe_fn_name(
+                    &self,
+                    input_data: #input_param,
+                    #ctx_param
+                ) -> my_grpc_extensions::GrpcSubscription<'_, TGrpcService, #input_param, #output_param, Self> {
+                    let channel = #get_channel #with_request_timeout .with_stream_idle_timeout(None) #with_stream_timeouts;
+
+                    channel
+                        .start_request(input_data)
+                        .subscribe(self)
+                }
+            });
+        }
     }
 
-    result
+    Ok(result)
+}
+
+/// Fields of the client struct with their initialization for rpcs with `cache_ttl_ms` override
+pub fn generate_response_cache_fields(
+    proto_file: &ProtoServiceDescription,
+    overrides: &HashMap<String, FnOverride>,
+) -> (Vec<proc_macro2::TokenStream>, Vec<proc_macro2::TokenStream>) {
+    let mut fields = Vec::new();
+    let mut init = Vec::new();
+
+    for rpc in &proto_file.rpc {
+        let Some(fn_override) = overrides.get(&rpc.name) else {
+            continue;
+        };
+
+        let Some(cache_ttl_ms) = fn_override.cache_ttl_ms else {
+            continue;
+        };
+
+        let field_name = get_response_cache_field_name(rpc);
+        let output_data_type = get_func_out_data_type(rpc.get_output_param().as_ref());
+
+        let with_max_entries = fn_override
+            .cache_max_entries
+            .map(|max_entries| quote::quote!(.with_max_entries(#max_entries)));
+
+        let with_stale_while_revalidate = fn_override.cache_stale_ms.map(|stale_ms| {
+            quote::quote!(.with_stale_while_revalidate(std::time::Duration::from_millis(#stale_ms)))
+        });
+
+        fields.push(quote::quote! {
+            #field_name: my_grpc_extensions::GrpcResponseCache<#output_data_type>
+        });
+
+        init.push(quote::quote! {
+            #field_name: my_grpc_extensions::GrpcResponseCache::new(
+                my_grpc_extensions::GrpcResponseCacheOptions::new(std::time::Duration::from_millis(#cache_ttl_ms))
+                    #with_max_entries
+                    #with_stale_while_revalidate
+            )
+        });
+    }
+
+    (fields, init)
+}
+
+fn get_response_cache_field_name(rpc: &ProtoRpc) -> proc_macro2::TokenStream {
+    proc_macro2::TokenStream::from_str(
+        format!("{}_cache", rpc.get_fn_name().as_snake_case()).as_str(),
+    )
+    .unwrap()
+}
+
+fn get_stream_timeouts(fn_override: &FnOverride) -> proc_macro2::TokenStream {
+    let with_idle_timeout = match fn_override.stream_idle_timeout_sec {
+        Some(0) => quote::quote!(.with_stream_idle_timeout(None)),
+        Some(idle_timeout_sec) => quote::quote!(
+            .with_stream_idle_timeout(Some(std::time::Duration::from_secs(#idle_timeout_sec)))
+        ),
+        None => quote::quote!(),
+    };
+
+    let with_deadline = match fn_override.stream_deadline_sec {
+        Some(deadline_sec) => {
+            quote::quote!(.with_stream_deadline(std::time::Duration::from_secs(#deadline_sec)))
+        }
+        None => quote::quote!(),
+    };
+
+    quote::quote!(#with_idle_timeout #with_deadline)
+}
+
+/// `None` if no limits are set
+pub fn generate_client_limits(
+    max_requests_per_second: Option<u32>,
+    max_in_flight: Option<usize>,
+    limit_queue_timeout_ms: Option<u64>,
+) -> Option<proc_macro2::TokenStream> {
+    if max_requests_per_second.is_none() && max_in_flight.is_none() {
+        return None;
+    }
+
+    let with_max_requests_per_second =
+        max_requests_per_second.map(|value| quote::quote!(.with_max_requests_per_second(#value)));
+
+    let with_max_in_flight = max_in_flight.map(|value| quote::quote!(.with_max_in_flight(#value)));
+
+    let with_queue_timeout = limit_queue_timeout_ms
+        .map(|value| quote::quote!(.with_queue_timeout(std::time::Duration::from_millis(#value))));
+
+    Some(quote::quote! {
+        my_grpc_extensions::GrpcClientLimits::new()
+            #with_max_requests_per_second
+            #with_max_in_flight
+            #with_queue_timeout
+    })
+}
+
+fn get_hedge_delay(fn_override: &FnOverride) -> proc_macro2::TokenStream {
+    match fn_override.hedge_delay_ms {
+        Some(hedge_delay_ms) => {
+            quote::quote!(.with_hedge_delay(std::time::Duration::from_millis(#hedge_delay_ms)))
+        }
+        None => quote::quote!(),
+    }
+}
+
+fn parse_option_value<T: FromStr>(
+    proto_file_param: &TokensObject,
+    rpc: &ProtoRpc,
+    option_name: &str,
+    value: &str,
+) -> Result<T, syn::Error> {
+    value.parse().map_err(|_| {
+        proto_file_param.throw_error_at_value_token(
+            format!(
+                "Invalid value '{}' of option {} for rpc {}",
+                value, option_name, rpc.name
+            )
+            .as_str(),
+        )
+    })
 }
 
 fn get_request_fn_name(input_param: Option<&ParamType<'_>>) -> proc_macro2::TokenStream {
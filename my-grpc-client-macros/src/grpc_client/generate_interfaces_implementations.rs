@@ -10,6 +10,12 @@ pub fn generate_interfaces_implementations(
     let mut result = Vec::new();
 
     for rpc in &proto_file.rpc {
+        let allow_deprecated = if rpc.is_deprecated() {
+            quote::quote!(#[allow(deprecated)])
+        } else {
+            quote::quote!()
+        };
+
         if let Some(input_param_type) = &rpc.get_input_param() {
             if let Some(output_param_type) = &rpc.get_output_param() {
                 let input_param_type_token = get_name_fn_param_type_token(&input_param_type);
@@ -35,6 +41,7 @@ pub fn generate_interfaces_implementations(
                         .unwrap();
 
                 let quote = quote::quote! {
+                    #allow_deprecated
                     #[async_trait::async_trait]
                     impl
                         #interface_name<
@@ -59,6 +66,8 @@ pub fn generate_interfaces_implementations(
                                 request.metadata_mut().insert("host", meta_data);
                             }
 
+                            my_grpc_extensions::apply_idempotency_key(&mut request);
+
                             let result = service.#fn_name(request).await?;
                             Ok(result.into_inner())
                         }
@@ -85,6 +94,7 @@ pub fn generate_interfaces_implementations(
                         .unwrap();
 
                 let quote = quote::quote! {
+                    #allow_deprecated
                     #[async_trait::async_trait]
                     impl
                         #interface_name<
@@ -109,6 +119,8 @@ pub fn generate_interfaces_implementations(
                                 request.metadata_mut().insert("host", meta_data);
                             }
 
+                            my_grpc_extensions::apply_idempotency_key(&mut request);
+
                             let result = service.#fn_name(request).await?;
                             Ok(result.into_inner())
                         }
@@ -136,6 +148,7 @@ pub fn generate_interfaces_implementations(
                         .unwrap();
 
                 let quote = quote::quote! {
+                    #allow_deprecated
                     #[async_trait::async_trait]
                     impl
                         #interface_name<
@@ -160,6 +173,8 @@ pub fn generate_interfaces_implementations(
                                 request.metadata_mut().insert("host", meta_data);
                             }
 
+                            my_grpc_extensions::apply_idempotency_key(&mut request);
+
                             let result = service.#fn_name(request).await?;
                             Ok(result.into_inner())
                         }
@@ -0,0 +1,2 @@
+mod proto_codegen;
+pub use proto_codegen::*;
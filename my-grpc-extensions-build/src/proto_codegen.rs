@@ -0,0 +1,345 @@
+use std::{
+    collections::BTreeMap,
+    path::{Path, PathBuf},
+    sync::atomic::{AtomicUsize, Ordering},
+};
+
+static OUT_DIR_NO: AtomicUsize = AtomicUsize::new(0);
+
+pub struct ProtoCodegen<'s> {
+    proto_file: &'s str,
+    build_client: bool,
+    build_server: bool,
+}
+
+impl<'s> ProtoCodegen<'s> {
+    pub fn new(proto_file: &'s str) -> Self {
+        Self {
+            proto_file,
+            build_client: false,
+            build_server: false,
+        }
+    }
+
+    pub fn build_client(mut self, value: bool) -> Self {
+        self.build_client = value;
+        self
+    }
+
+    pub fn build_server(mut self, value: bool) -> Self {
+        self.build_server = value;
+        self
+    }
+
+    /// Runs prost/tonic codegen for the proto file and returns generated Rust code.
+    /// Code of each proto package is placed into its own module, items of the package
+    /// of the proto file are re-exported from the root.
+    /// Requires `protoc` to be available (PATH or PROTOC env variable).
+    pub fn generate(&self) -> Result<String, String> {
+        let proto_file = Path::new(self.proto_file);
+
+        let include_dir = get_include_dir(proto_file);
+
+        let out_dir = std::env::temp_dir().join(format!(
+            "my-grpc-extensions-build-{}-{}",
+            std::process::id(),
+            OUT_DIR_NO.fetch_add(1, Ordering::SeqCst)
+        ));
+
+        if let Err(err) = std::fs::create_dir_all(&out_dir) {
+            return Err(format!(
+                "Can not create codegen dir {:?}. Err: {:?}",
+                out_dir, err
+            ));
+        }
+
+        let result = self.generate_to_dir(proto_file, include_dir.as_path(), out_dir.as_path());
+
+        let _ = std::fs::remove_dir_all(&out_dir);
+
+        result
+    }
+
+    /// Generated code wrapped into `pub mod #module_name`. Used by the macros:
+    /// the module includes the proto files as bytes, so the crate is recompiled once they are changed
+    pub fn generate_module(&self, module_name: &str) -> Result<String, String> {
+        let code = self.generate()?;
+
+        let mut result = format!("pub mod {} {{\n", module_name);
+
+        let proto_file = Path::new(self.proto_file);
+
+        for file in get_proto_files(proto_file, get_include_dir(proto_file).as_path()) {
+            if let Some(file) = file.to_str() {
+                result.push_str(format!("const _: &[u8] = include_bytes!({:?});\n", file).as_str());
+            }
+        }
+
+        result.push_str(code.as_str());
+        result.push_str("}\n");
+
+        Ok(result)
+    }
+
+    fn generate_to_dir(
+        &self,
+        proto_file: &Path,
+        include_dir: &Path,
+        out_dir: &Path,
+    ) -> Result<String, String> {
+        self.compile_protos(proto_file, include_dir, out_dir)?;
+
+        let dir = match std::fs::read_dir(out_dir) {
+            Ok(dir) => dir,
+            Err(err) => {
+                return Err(format!(
+                    "Can not read codegen dir {:?}. Err: {:?}",
+                    out_dir, err
+                ))
+            }
+        };
+
+        let mut root = PackageModule::default();
+
+        for entry in dir {
+            let entry = entry.map_err(|err| format!("{:?}", err))?;
+            let path = entry.path();
+
+            if !path.extension().map(|ext| ext == "rs").unwrap_or(false) {
+                continue;
+            }
+
+            let package = match path.file_stem().and_then(|stem| stem.to_str()) {
+                Some(package) => package.to_string(),
+                None => continue,
+            };
+
+            match std::fs::read_to_string(&path) {
+                Ok(content) => {
+                    // prost names the file of a proto without package as `_.rs`
+                    let package_path: Vec<&str> = if package == "_" {
+                        vec![]
+                    } else {
+                        package.split('.').collect()
+                    };
+
+                    root.insert(package_path.as_slice(), content);
+                }
+                Err(err) => {
+                    return Err(format!(
+                        "Can not read generated file {:?}. Err: {:?}",
+                        path, err
+                    ))
+                }
+            }
+        }
+
+        let mut result = String::new();
+
+        if let Some(package) = read_package(proto_file) {
+            result.push_str(format!("pub use {}::*;\n", package.replace('.', "::")).as_str());
+        }
+
+        root.write(&mut result);
+
+        Ok(result)
+    }
+
+    #[cfg(feature = "codegen")]
+    fn compile_protos(
+        &self,
+        proto_file: &Path,
+        include_dir: &Path,
+        out_dir: &Path,
+    ) -> Result<(), String> {
+        tonic_prost_build::configure()
+            .build_client(self.build_client)
+            .build_server(self.build_server)
+            // google.protobuf wrappers (BoolValue, Int64Value, ...) are mapped to primitive types
+            // by prost, rpc params use them as is instead of `super::bool`
+            .with_extended_rust_types(true)
+            .emit_rerun_if_changed(false)
+            .out_dir(out_dir)
+            .compile_protos(&[proto_file], &[include_dir])
+            .map_err(|err| {
+                format!(
+                    "Can not generate code for proto file {}. Err: {}",
+                    self.proto_file, err
+                )
+            })
+    }
+
+    #[cfg(not(feature = "codegen"))]
+    fn compile_protos(
+        &self,
+        _proto_file: &Path,
+        _include_dir: &Path,
+        _out_dir: &Path,
+    ) -> Result<(), String> {
+        Err("generate_proto requires feature 'with-proto-codegen' to be enabled".to_string())
+    }
+}
+
+/// Generated code of the proto package. Nested packages are nested modules,
+/// so `super::` paths between packages generated by prost are resolved
+#[derive(Default)]
+struct PackageModule {
+    code: String,
+    children: BTreeMap<String, PackageModule>,
+}
+
+impl PackageModule {
+    fn insert(&mut self, package_path: &[&str], code: String) {
+        match package_path.split_first() {
+            Some((name, rest)) => self
+                .children
+                .entry(name.to_string())
+                .or_default()
+                .insert(rest, code),
+            None => {
+                self.code.push_str(code.as_str());
+                self.code.push('\n');
+            }
+        }
+    }
+
+    fn write(&self, out: &mut String) {
+        out.push_str(self.code.as_str());
+
+        for (name, child) in &self.children {
+            out.push_str(format!("pub mod {} {{\n", name).as_str());
+            child.write(out);
+            out.push_str("}\n");
+        }
+    }
+}
+
+fn get_include_dir(proto_file: &Path) -> PathBuf {
+    match proto_file.parent() {
+        Some(parent) if parent.as_os_str().len() > 0 => parent.to_path_buf(),
+        _ => PathBuf::from("."),
+    }
+}
+
+fn read_package(proto_file: &Path) -> Option<String> {
+    let content = std::fs::read_to_string(proto_file).ok()?;
+
+    content.lines().find_map(|line| {
+        let package = line.trim().strip_prefix("package ")?;
+        Some(package.trim().trim_end_matches(';').trim().to_string())
+    })
+}
+
+/// The proto file with the imported files found in the include dir
+fn get_proto_files(proto_file: &Path, include_dir: &Path) -> Vec<PathBuf> {
+    let mut result: Vec<PathBuf> = Vec::new();
+    let mut to_check = vec![proto_file.to_path_buf()];
+
+    while let Some(file) = to_check.pop() {
+        let Ok(file) = std::fs::canonicalize(file) else {
+            continue;
+        };
+
+        if result.contains(&file) {
+            continue;
+        }
+
+        if let Ok(content) = std::fs::read_to_string(&file) {
+            for line in content.lines() {
+                if let Some(import) = line.trim().strip_prefix("import ") {
+                    let import = import.trim_end_matches(';').trim();
+                    let import = import.trim_start_matches("public ").trim();
+                    to_check.push(include_dir.join(import.trim_matches('"')));
+                }
+            }
+        }
+
+        result.push(file);
+    }
+
+    result
+}
+
+#[cfg(test)]
+mod tests {
+
+    #[test]
+    fn test_package_modules() {
+        let mut root = super::PackageModule::default();
+
+        root.insert(&["a", "b"], "pub struct B { c: super::c::C }".to_string());
+        root.insert(&["a", "c"], "pub struct C;".to_string());
+        root.insert(&[], "pub struct NoPackage;".to_string());
+
+        let mut result = String::new();
+        root.write(&mut result);
+
+        assert_eq!(
+            result,
+            "pub struct NoPackage;\npub mod a {\npub mod b {\npub struct B { c: super::c::C }\n}\npub mod c {\npub struct C;\n}\n}\n"
+        );
+    }
+
+    const WRAPPERS_PROTO: &str = r#"
+syntax = "proto3";
+package wrappers;
+import "google/protobuf/empty.proto";
+import "google/protobuf/wrappers.proto";
+import "google/protobuf/timestamp.proto";
+
+service WrappersService {
+    rpc GetBool(google.protobuf.BoolValue) returns (google.protobuf.Int64Value);
+    rpc GetBytes(google.protobuf.BytesValue) returns (google.protobuf.StringValue);
+    rpc GetTime(google.protobuf.Empty) returns (google.protobuf.Timestamp);
+    rpc GetDouble(google.protobuf.UInt32Value) returns (google.protobuf.DoubleValue);
+    rpc SetFloat(google.protobuf.FloatValue) returns (google.protobuf.Empty);
+}
+"#;
+
+    /// Runs the real codegen (requires protoc) and checks that rpc param types
+    /// read from the proto file are the same types tonic uses in the generated code
+    #[test]
+    fn test_well_known_types_of_generated_code() {
+        let dir = std::env::temp_dir().join(format!(
+            "my-grpc-extensions-build-test-{}",
+            std::process::id()
+        ));
+        std::fs::create_dir_all(&dir).unwrap();
+
+        let proto_file = dir.join("wrappers.proto");
+        std::fs::write(&proto_file, WRAPPERS_PROTO).unwrap();
+        let proto_file = proto_file.to_str().unwrap().to_string();
+
+        let code = super::ProtoCodegen::new(proto_file.as_str())
+            .build_client(true)
+            .build_server(true)
+            .generate();
+
+        let description =
+            proto_file_reader::ProtoServiceDescription::read_proto_file(proto_file.as_str());
+
+        let _ = std::fs::remove_dir_all(&dir);
+
+        let code = code.unwrap();
+
+        assert!(!code.contains("super::bool"));
+        assert!(!code.contains("prost_types::BoolValue"));
+
+        assert_eq!(description.rpc.len(), 5);
+
+        for rpc in &description.rpc {
+            let input_param = rpc.get_input_param().unwrap();
+            let output_param = rpc.get_output_param().unwrap();
+
+            let request = format!("tonic::Request<{}>", input_param.get_name());
+            let response = format!("tonic::Response<{}>", output_param.get_name());
+
+            assert!(code.contains(request.as_str()), "{} is not found", request);
+            assert!(
+                code.contains(response.as_str()),
+                "{} is not found",
+                response
+            );
+        }
+    }
+}
@@ -0,0 +1,213 @@
+use std::time::Duration;
+
+use futures::future::Either;
+use tokio::{sync::mpsc, task::JoinHandle, time::Instant};
+
+use crate::{StreamSendError, StreamedResponseProducer};
+
+#[derive(Debug, Clone, Copy)]
+pub struct BatchingProducerOptions {
+    pub max_batch_size: usize,
+    /// Max time the first item of the batch waits before the batch is flushed
+    pub max_latency: Duration,
+}
+
+impl Default for BatchingProducerOptions {
+    fn default() -> Self {
+        Self {
+            max_batch_size: 100,
+            max_latency: Duration::from_millis(50),
+        }
+    }
+}
+
+enum BatchCommand<TItem> {
+    Item(TItem),
+    Flush,
+}
+
+/// Accumulates live items and sends them to the stream as chunks
+/// once `max_batch_size` is reached or `max_latency` is elapsed.
+pub struct BatchingStreamedResponseProducer<TItem: Send + 'static> {
+    tx: mpsc::Sender<BatchCommand<TItem>>,
+    background_task: JoinHandle<()>,
+}
+
+impl<TItem: Send + 'static> BatchingStreamedResponseProducer<TItem> {
+    pub fn new<TChunk: Send + Sync + 'static>(
+        producer: StreamedResponseProducer<TChunk>,
+        options: BatchingProducerOptions,
+        mapper: impl Fn(Vec<TItem>) -> TChunk + Send + Sync + 'static,
+    ) -> Self {
+        let max_batch_size = options.max_batch_size.max(1);
+        let (tx, rx) = mpsc::channel(max_batch_size);
+
+        let background_task = tokio::spawn(batching_loop(
+            rx,
+            producer,
+            max_batch_size,
+            options.max_latency,
+            mapper,
+        ));
+
+        Self {
+            tx,
+            background_task,
+        }
+    }
+
+    pub async fn send(&self, item: TItem) -> Result<(), StreamSendError> {
+        self.tx
+            .send(BatchCommand::Item(item))
+            .await
+            .map_err(|_| StreamSendError::Closed)
+    }
+
+    /// Sends accumulated items without waiting for max_latency
+    pub async fn flush(&self) -> Result<(), StreamSendError> {
+        self.tx
+            .send(BatchCommand::Flush)
+            .await
+            .map_err(|_| StreamSendError::Closed)
+    }
+
+    /// Client is disconnected or the stream is failed to be written
+    pub fn is_closed(&self) -> bool {
+        self.tx.is_closed()
+    }
+
+    /// Flushes accumulated items and waits until they are sent to the stream
+    pub async fn finish(self) {
+        drop(self.tx);
+        let _ = self.background_task.await;
+    }
+}
+
+async fn batching_loop<TItem: Send + 'static, TChunk: Send + Sync + 'static>(
+    mut rx: mpsc::Receiver<BatchCommand<TItem>>,
+    producer: StreamedResponseProducer<TChunk>,
+    max_batch_size: usize,
+    max_latency: Duration,
+    mapper: impl Fn(Vec<TItem>) -> TChunk + Send + Sync + 'static,
+) {
+    let mut batch = Vec::with_capacity(max_batch_size);
+
+    loop {
+        let first_item = {
+            let closed = std::pin::pin!(producer.closed());
+            let next = std::pin::pin!(rx.recv());
+
+            match futures::future::select(closed, next).await {
+                Either::Left(_) => return,
+                Either::Right((command, _)) => command,
+            }
+        };
+
+        let mut source_is_over = false;
+
+        match first_item {
+            Some(BatchCommand::Item(item)) => batch.push(item),
+            Some(BatchCommand::Flush) => continue,
+            None => return,
+        }
+
+        let deadline = Instant::now() + max_latency;
+
+        while batch.len() < max_batch_size {
+            match tokio::time::timeout_at(deadline, rx.recv()).await {
+                Ok(Some(BatchCommand::Item(item))) => batch.push(item),
+                Ok(Some(BatchCommand::Flush)) => break,
+                Ok(None) => {
+                    source_is_over = true;
+                    break;
+                }
+                Err(_) => break,
+            }
+        }
+
+        let mut chunk = Vec::with_capacity(max_batch_size);
+        std::mem::swap(&mut batch, &mut chunk);
+
+        if producer.send(mapper(chunk)).await.is_err() || source_is_over {
+            return;
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::Duration;
+
+    use futures::StreamExt;
+
+    use super::BatchingProducerOptions;
+    use crate::StreamedResponseWriter;
+
+    #[test]
+    fn test_items_are_flushed_by_size_and_on_finish() {
+        let runtime = tokio::runtime::Builder::new_current_thread()
+            .enable_time()
+            .build()
+            .unwrap();
+
+        runtime.block_on(async {
+            let writer = StreamedResponseWriter::<Vec<i32>>::new(10);
+
+            let producer = writer.get_batching_stream_producer(
+                BatchingProducerOptions {
+                    max_batch_size: 3,
+                    max_latency: Duration::from_secs(10),
+                },
+                |items| items,
+            );
+
+            let mut stream = writer.get_result().unwrap().into_inner();
+
+            for i in 0..7 {
+                producer.send(i).await.unwrap();
+            }
+
+            producer.finish().await;
+
+            let mut result = Vec::new();
+            while let Some(item) = stream.next().await {
+                result.push(item.unwrap());
+            }
+
+            assert_eq!(result, vec![vec![0, 1, 2], vec![3, 4, 5], vec![6]]);
+        });
+    }
+
+    #[test]
+    fn test_items_are_flushed_by_latency() {
+        let runtime = tokio::runtime::Builder::new_current_thread()
+            .enable_time()
+            .build()
+            .unwrap();
+
+        runtime.block_on(async {
+            let writer = StreamedResponseWriter::<Vec<i32>>::new(10);
+
+            let producer = writer.get_batching_stream_producer(
+                BatchingProducerOptions {
+                    max_batch_size: 100,
+                    max_latency: Duration::from_millis(10),
+                },
+                |items| items,
+            );
+
+            let mut stream = writer.get_result().unwrap().into_inner();
+
+            producer.send(1).await.unwrap();
+            producer.send(2).await.unwrap();
+
+            let chunk = tokio::time::timeout(Duration::from_secs(1), stream.next())
+                .await
+                .unwrap()
+                .unwrap()
+                .unwrap();
+
+            assert_eq!(chunk, vec![1, 2]);
+        });
+    }
+}
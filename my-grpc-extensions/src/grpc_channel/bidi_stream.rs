@@ -0,0 +1,301 @@
+use std::{
+    future::Future,
+    pin::Pin,
+    task::{Context, Poll},
+    time::Duration,
+};
+
+use futures_util::{future::Either, StreamExt};
+
+use crate::{
+    GrpcChannel, GrpcReadError, RequestWithInputAsStreamWithResponseAsStreamGrpcExecutor,
+    StreamedRequest, StreamedRequestError, StreamedRequestProducer, StreamedResponse,
+};
+
+type ConnectFuture<'s, TResponse> =
+    Pin<Box<dyn Future<Output = Result<StreamedResponse<TResponse>, GrpcReadError>> + Send + 's>>;
+
+/// Sending half of the bidirectional stream. Dropping it half-closes the stream
+pub struct BidiStreamSender<TRequest: Send + Sync + 'static + Clone> {
+    producer: StreamedRequestProducer<TRequest>,
+    send_timeout: Option<Duration>,
+}
+
+impl<TRequest: Send + Sync + 'static + Clone> BidiStreamSender<TRequest> {
+    /// `None` - wait while the server does not read items
+    pub fn set_send_timeout(&mut self, send_timeout: Option<Duration>) {
+        self.send_timeout = send_timeout;
+    }
+
+    pub async fn send(&self, item: TRequest) -> Result<(), StreamedRequestError> {
+        let Some(send_timeout) = self.send_timeout else {
+            return self.producer.send(item).await;
+        };
+
+        match tokio::time::timeout(send_timeout, self.producer.send(item)).await {
+            Ok(result) => result,
+            Err(_) => Err(StreamedRequestError::Timeout),
+        }
+    }
+
+    pub fn is_closed(&self) -> bool {
+        self.producer.is_closed()
+    }
+
+    /// Half-closes the stream once all the sent items are taken by the transport.
+    /// Responses can still be received
+    pub async fn close_send(self) -> Result<(), StreamedRequestError> {
+        self.producer.finish().await
+    }
+}
+
+enum BidiStreamReceiverState<'s, TResponse> {
+    Connecting(ConnectFuture<'s, TResponse>),
+    Connected(Box<StreamedResponse<TResponse>>),
+    Failed(Option<GrpcReadError>),
+}
+
+/// Receiving half of the bidirectional stream. Rpc is connected while the receiver is polled,
+/// so it has to be read even if the server is not expected to respond before the first items are sent
+pub struct BidiStreamReceiver<'s, TResponse> {
+    state: BidiStreamReceiverState<'s, TResponse>,
+    idle_timeout: Option<Option<Duration>>,
+}
+
+impl<'s, TResponse> BidiStreamReceiver<'s, TResponse> {
+    fn new(connect: ConnectFuture<'s, TResponse>) -> Self {
+        Self {
+            state: BidiStreamReceiverState::Connecting(connect),
+            idle_timeout: None,
+        }
+    }
+
+    pub async fn recv(&mut self) -> Option<Result<TResponse, GrpcReadError>> {
+        self.next().await
+    }
+
+    /// `None` - server can be silent forever
+    pub fn set_idle_timeout(&mut self, idle_timeout: Option<Duration>) {
+        match &mut self.state {
+            BidiStreamReceiverState::Connected(stream) => stream.set_idle_timeout(idle_timeout),
+            _ => self.idle_timeout = Some(idle_timeout),
+        }
+    }
+
+    /// Waits for the rpc to be connected to use the rest of `StreamedResponse` api
+    pub async fn connect(mut self) -> Result<StreamedResponse<TResponse>, GrpcReadError> {
+        futures_util::future::poll_fn(|cx| self.poll_connect(cx)).await;
+
+        match self.state {
+            BidiStreamReceiverState::Connected(stream) => Ok(*stream),
+            BidiStreamReceiverState::Failed(Some(err)) => Err(err),
+            _ => Err(GrpcReadError::TonicStatus(tonic::Status::unavailable(
+                "Bidi stream is failed to connect",
+            ))),
+        }
+    }
+
+    /// Ready once the rpc is connected or failed to connect
+    fn poll_connect(&mut self, cx: &mut Context<'_>) -> Poll<()> {
+        let BidiStreamReceiverState::Connecting(connect) = &mut self.state else {
+            return Poll::Ready(());
+        };
+
+        let Poll::Ready(result) = connect.as_mut().poll(cx) else {
+            return Poll::Pending;
+        };
+
+        self.state = match result {
+            Ok(mut stream) => {
+                if let Some(idle_timeout) = self.idle_timeout.take() {
+                    stream.set_idle_timeout(idle_timeout);
+                }
+                BidiStreamReceiverState::Connected(Box::new(stream))
+            }
+            Err(err) => BidiStreamReceiverState::Failed(Some(err)),
+        };
+
+        Poll::Ready(())
+    }
+
+    /// Keeps connecting the rpc while the future is running
+    async fn drive<TResult>(&mut self, future: impl Future<Output = TResult>) -> TResult {
+        let future = std::pin::pin!(future);
+        let connect = futures_util::future::poll_fn(|cx| self.poll_connect(cx));
+
+        match futures_util::future::select(future, std::pin::pin!(connect)).await {
+            Either::Left((result, _)) => result,
+            Either::Right((_, future)) => future.await,
+        }
+    }
+}
+
+/// Error of connecting is yielded as the first item
+impl<'s, TResponse> futures_util::Stream for BidiStreamReceiver<'s, TResponse> {
+    type Item = Result<TResponse, GrpcReadError>;
+
+    fn poll_next(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<Self::Item>> {
+        let this = self.get_mut();
+
+        if this.poll_connect(cx).is_pending() {
+            return Poll::Pending;
+        }
+
+        match &mut this.state {
+            BidiStreamReceiverState::Connected(stream) => Pin::new(stream.as_mut()).poll_next(cx),
+            BidiStreamReceiverState::Failed(err) => Poll::Ready(err.take().map(Err)),
+            BidiStreamReceiverState::Connecting(_) => Poll::Pending,
+        }
+    }
+}
+
+/// Duplex handle of the bidirectional rpc. Halves can be used concurrently after `split`.
+/// Dropping the handle cancels the rpc.
+pub struct BidiStream<'s, TRequest: Send + Sync + 'static + Clone, TResponse> {
+    sender: BidiStreamSender<TRequest>,
+    receiver: BidiStreamReceiver<'s, TResponse>,
+}
+
+impl<'s, TRequest: Send + Sync + 'static + Clone, TResponse> BidiStream<'s, TRequest, TResponse> {
+    pub fn new(
+        sender: BidiStreamSender<TRequest>,
+        receiver: BidiStreamReceiver<'s, TResponse>,
+    ) -> Self {
+        Self { sender, receiver }
+    }
+
+    /// Rpc is connected while waiting for the item to be sent
+    pub async fn send(&mut self, item: TRequest) -> Result<(), StreamedRequestError> {
+        self.receiver.drive(self.sender.send(item)).await
+    }
+
+    pub async fn recv(&mut self) -> Option<Result<TResponse, GrpcReadError>> {
+        self.receiver.recv().await
+    }
+
+    pub fn set_send_timeout(&mut self, send_timeout: Option<Duration>) {
+        self.sender.set_send_timeout(send_timeout);
+    }
+
+    /// `None` - server can be silent forever
+    pub fn set_recv_idle_timeout(&mut self, idle_timeout: Option<Duration>) {
+        self.receiver.set_idle_timeout(idle_timeout);
+    }
+
+    pub fn split(
+        self,
+    ) -> (
+        BidiStreamSender<TRequest>,
+        BidiStreamReceiver<'s, TResponse>,
+    ) {
+        (self.sender, self.receiver)
+    }
+
+    /// Half-closes the sending side. Rest of the responses can be read from the returned receiver
+    pub async fn close_send(
+        mut self,
+    ) -> Result<BidiStreamReceiver<'s, TResponse>, StreamedRequestError> {
+        self.receiver.drive(self.sender.close_send()).await?;
+        Ok(self.receiver)
+    }
+}
+
+impl<TService: Send + Sync + 'static> GrpcChannel<TService> {
+    /// Returns without waiting for the server. Rpc is connected in the background of `send` and `recv`.
+    /// Request timeout of the channel is used as send timeout and as idle timeout of receiving
+    pub fn open_bidi_stream<
+        's,
+        TRequest: Send + Sync + 'static + Clone,
+        TResponse: Send + Sync + 'static,
+        TExecutor: RequestWithInputAsStreamWithResponseAsStreamGrpcExecutor<TService, TRequest, TResponse>
+            + Send
+            + Sync
+            + 'static,
+    >(
+        mut self,
+        grpc_executor: &'s TExecutor,
+    ) -> BidiStream<'s, TRequest, TResponse> {
+        let request = StreamedRequest::new_as_stream();
+
+        let sender = BidiStreamSender {
+            producer: request.get_producer(),
+            send_timeout: Some(self.request_timeout),
+        };
+
+        let connect = async move {
+            self.execute_input_as_stream_response_as_stream(&request, grpc_executor)
+                .await
+        };
+
+        BidiStream::new(sender, BidiStreamReceiver::new(Box::pin(connect)))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::Duration;
+
+    use super::*;
+
+    #[test]
+    fn test_send_timeout() {
+        let rt = tokio::runtime::Builder::new_current_thread()
+            .enable_time()
+            .build()
+            .unwrap();
+
+        rt.block_on(async {
+            let request = StreamedRequest::new_as_stream().set_channel_size(1);
+
+            let sender = BidiStreamSender {
+                producer: request.get_producer(),
+                send_timeout: Some(Duration::from_millis(10)),
+            };
+
+            assert_eq!(sender.send(1).await, Ok(()));
+            assert_eq!(sender.send(2).await, Err(StreamedRequestError::Timeout));
+
+            drop(request);
+            assert!(sender.is_closed());
+            assert_eq!(sender.send(3).await, Err(StreamedRequestError::Closed));
+        });
+    }
+
+    #[test]
+    fn test_send_before_connected() {
+        let rt = tokio::runtime::Builder::new_current_thread()
+            .enable_time()
+            .build()
+            .unwrap();
+
+        rt.block_on(async {
+            let request = StreamedRequest::new_as_stream().set_channel_size(1);
+
+            let sender = BidiStreamSender {
+                producer: request.get_producer(),
+                send_timeout: Some(Duration::from_millis(10)),
+            };
+
+            let (connected_tx, connected_rx) = tokio::sync::oneshot::channel::<()>();
+
+            let connect: ConnectFuture<'static, i32> = Box::pin(async move {
+                let _ = connected_rx.await;
+                drop(request);
+                Err(GrpcReadError::Timeout)
+            });
+
+            let mut bidi_stream = BidiStream::new(sender, BidiStreamReceiver::new(connect));
+
+            assert_eq!(bidi_stream.send(1).await, Ok(()));
+
+            connected_tx.send(()).unwrap();
+
+            assert_eq!(bidi_stream.send(2).await, Err(StreamedRequestError::Closed));
+            assert!(matches!(
+                bidi_stream.recv().await,
+                Some(Err(GrpcReadError::Timeout))
+            ));
+            assert!(bidi_stream.recv().await.is_none());
+        });
+    }
+}
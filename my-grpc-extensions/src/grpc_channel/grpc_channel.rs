@@ -5,15 +5,22 @@ use std::{sync::Arc, time::Duration};
 use tonic::transport::Channel;
 
 use crate::{
-    GrpcChannelHolder, GrpcClientSettings, GrpcReadError, GrpcServiceFactory, RequestBuilder,
-    RequestBuilderWithInputStream, StreamedRequest,
+    GrpcChannelHolder, GrpcClientLimiter, GrpcClientLimiterPermit, GrpcClientSettings,
+    GrpcReadError, GrpcServiceFactory, GrpcStreamTimeouts, RequestBuilder,
+    RequestBuilderWithInputStream, StreamReadLimits, StreamedRequest, StreamedResponse,
 };
 
-use super::*;
+use super::{hedged_request::execute_hedged_futures, *};
 
 pub struct GrpcChannel<TService: Send + Sync + 'static> {
     grpc_channel_holder: Arc<GrpcChannelHolder>,
+    hedge_channel_holder: Arc<GrpcChannelHolder>,
     pub request_timeout: Duration,
+    hedge_delay: Option<Duration>,
+    max_retry_delay: Option<Duration>,
+    stream_timeouts: Option<GrpcStreamTimeouts>,
+    stream_read_limits: StreamReadLimits,
+    limiters: Vec<Arc<GrpcClientLimiter>>,
     service_factory: Arc<dyn GrpcServiceFactory<TService> + Send + Sync + 'static>,
     get_grpc_address: Arc<dyn GrpcClientSettings + Send + Sync + 'static>,
     #[cfg(feature = "with-telemetry")]
@@ -32,8 +39,14 @@ impl<TService: Send + Sync + 'static> GrpcChannel<TService> {
         #[cfg(feature = "with-ssh")] ssh_target: crate::SshTarget,
     ) -> Self {
         Self {
+            hedge_channel_holder: Arc::new(GrpcChannelHolder::new()),
             grpc_channel_holder,
             request_timeout,
+            hedge_delay: None,
+            max_retry_delay: None,
+            stream_timeouts: None,
+            stream_read_limits: StreamReadLimits::default(),
+            limiters: Vec::new(),
             service_factory,
             get_grpc_address,
             #[cfg(feature = "with-telemetry")]
@@ -43,6 +56,106 @@ impl<TService: Send + Sync + 'static> GrpcChannel<TService> {
         }
     }
 
+    pub fn with_request_timeout(mut self, request_timeout: Duration) -> Self {
+        self.request_timeout = request_timeout;
+        self
+    }
+
+    /// Unary request is sent through the hedge channel as well once there is no response within the delay.
+    /// Must be used only for idempotent requests
+    pub fn with_hedge_delay(mut self, hedge_delay: Duration) -> Self {
+        self.hedge_delay = Some(hedge_delay);
+        self
+    }
+
+    /// Hedged requests are sent through a separate connection, so reconnecting to the hedge url does not affect the primary one
+    pub fn with_hedge_channel_holder(
+        mut self,
+        hedge_channel_holder: Arc<GrpcChannelHolder>,
+    ) -> Self {
+        self.hedge_channel_holder = hedge_channel_holder;
+        self
+    }
+
+    pub fn get_hedge_delay(&self) -> Option<Duration> {
+        self.hedge_delay
+    }
+
+    /// Max time to wait for before the retry once the server sends `RetryInfo`.
+    /// `None` - request timeout is used as max retry delay
+    pub fn with_max_retry_delay(mut self, max_retry_delay: Option<Duration>) -> Self {
+        self.max_retry_delay = max_retry_delay;
+        self
+    }
+
+    /// `RetryInfo` delay of the error capped with the max retry delay
+    pub fn get_retry_delay(&self, err: &GrpcReadError) -> Option<Duration> {
+        let max_retry_delay = self.max_retry_delay.unwrap_or(self.request_timeout);
+        Some(err.get_retry_delay()?.min(max_retry_delay))
+    }
+
+    /// `None` - server stream can be idle forever. Request timeout is used as idle timeout by default
+    pub fn with_stream_idle_timeout(mut self, idle_timeout: Option<Duration>) -> Self {
+        let timeouts = self.get_stream_timeouts();
+        self.stream_timeouts = Some(timeouts.with_idle_timeout(idle_timeout));
+        self
+    }
+
+    /// Max duration of the whole server stream
+    pub fn with_stream_deadline(mut self, deadline: Duration) -> Self {
+        let timeouts = self.get_stream_timeouts();
+        self.stream_timeouts = Some(timeouts.with_deadline(deadline));
+        self
+    }
+
+    pub fn get_stream_timeouts(&self) -> GrpcStreamTimeouts {
+        match self.stream_timeouts {
+            Some(stream_timeouts) => stream_timeouts,
+            None => self.request_timeout.into(),
+        }
+    }
+
+    pub fn with_stream_read_limits(mut self, stream_read_limits: StreamReadLimits) -> Self {
+        self.stream_read_limits = stream_read_limits;
+        self
+    }
+
+    /// Request waits for all the limiters before it is sent
+    pub fn with_limiter(mut self, limiter: Arc<GrpcClientLimiter>) -> Self {
+        self.limiters.push(limiter);
+        self
+    }
+
+    pub fn with_method_limiter(self, limiter: Option<Arc<GrpcClientLimiter>>) -> Self {
+        match limiter {
+            Some(limiter) => self.with_limiter(limiter),
+            None => self,
+        }
+    }
+
+    /// Permits have to be kept until the request is finished
+    pub async fn acquire_limits(&self) -> Result<Vec<GrpcClientLimiterPermit>, GrpcReadError> {
+        let mut result = Vec::with_capacity(self.limiters.len());
+
+        for limiter in &self.limiters {
+            result.push(limiter.acquire().await?);
+        }
+
+        Ok(result)
+    }
+
+    /// Permits are kept until the stream is dropped
+    pub(crate) fn create_streamed_response<TResponse>(
+        &self,
+        stream: tonic::Streaming<TResponse>,
+        permits: Vec<GrpcClientLimiterPermit>,
+    ) -> StreamedResponse<TResponse> {
+        StreamedResponse::new(stream, self.get_stream_timeouts())
+            .with_permits(permits)
+            .with_read_limits(self.stream_read_limits)
+            .with_request_timeout(self.request_timeout)
+    }
+
     pub async fn get_connect_url(&self) -> GrpcConnectUrl {
         let settings = self
             .get_grpc_address
@@ -74,29 +187,40 @@ impl<TService: Send + Sync + 'static> GrpcChannel<TService> {
         Ok(result)
     }
 
+    /// Channel of hedged requests. Connects to the hedge url if it is configured
+    pub async fn get_hedge_channel(&self) -> Result<Channel, GrpcReadError> {
+        if let Some(channel) = self.hedge_channel_holder.get().await {
+            return Ok(channel);
+        }
+
+        let service_name = self.service_factory.get_service_name();
+
+        let connect_url: GrpcConnectUrl =
+            match self.get_grpc_address.get_hedge_grpc_url(service_name).await {
+                Some(grpc_url) => grpc_url.url.into(),
+                None => self.get_connect_url().await,
+            };
+
+        let result = self
+            .hedge_channel_holder
+            .create_channel(
+                connect_url,
+                service_name,
+                self.request_timeout,
+                #[cfg(feature = "with-ssh")]
+                self.ssh_target.get_value().await,
+            )
+            .await?;
+
+        Ok(result)
+    }
+
     pub async fn drop_dead_channel(&self, err: String) {
         self.grpc_channel_holder.drop_channel(err).await;
     }
 
     pub async fn drop_channel_if_needed(&self, err: &GrpcReadError) -> bool {
-        let remove = match err {
-            GrpcReadError::TonicStatus(status) => {
-                let code = status.code();
-
-                if code == tonic::Code::Unknown {
-                    true
-                } else {
-                    false
-                }
-            }
-            _ => false,
-        };
-
-        if remove {
-            self.drop_dead_channel(format!("{:?}", err)).await;
-        }
-
-        remove
+        drop_channel_if_needed(&self.grpc_channel_holder, err).await
     }
 
     pub async fn get_service(
@@ -134,38 +258,94 @@ impl<TService: Send + Sync + 'static> GrpcChannel<TService> {
         TResponse: Send + Sync + 'static,
         TExecutor: RequestResponseGrpcExecutor<TService, TRequest, TResponse> + Send + Sync + 'static,
     >(
-        &mut self,
+        &self,
         request_data: TRequest,
         grpc_executor: &TExecutor,
     ) -> Result<TResponse, GrpcReadError> {
-        let service = self
-            .get_service(
-                #[cfg(feature = "with-telemetry")]
-                &self.ctx,
-            )
-            .await?;
+        let _permits = self.acquire_limits().await?;
+
+        let channel = self.get_channel().await?;
+
+        self.execute_with_channel(
+            &self.grpc_channel_holder,
+            channel,
+            request_data,
+            grpc_executor,
+        )
+        .await
+    }
+
+    /// Channel of the holder is dropped once the request is timed out or the channel looks dead
+    async fn execute_with_channel<
+        TRequest: Send + Sync + 'static,
+        TResponse: Send + Sync + 'static,
+        TExecutor: RequestResponseGrpcExecutor<TService, TRequest, TResponse> + Send + Sync + 'static,
+    >(
+        &self,
+        channel_holder: &GrpcChannelHolder,
+        channel: Channel,
+        request_data: TRequest,
+        grpc_executor: &TExecutor,
+    ) -> Result<TResponse, GrpcReadError> {
+        let service = self.service_factory.create_service(
+            channel,
+            #[cfg(feature = "with-telemetry")]
+            &self.ctx,
+        );
 
         let future = grpc_executor.execute(service, request_data);
 
         let result = tokio::time::timeout(self.request_timeout, future).await;
 
         if result.is_err() {
-            self.drop_dead_channel("Timeout".to_string()).await;
+            channel_holder.drop_channel("Timeout".to_string()).await;
             return Err(GrpcReadError::Timeout);
         }
 
-        let result = result.unwrap();
-
-        match result {
+        match result.unwrap() {
             Ok(result) => Ok(result),
             Err(err) => {
                 let err = err.into();
-                self.drop_channel_if_needed(&err).await;
+                drop_channel_if_needed(channel_holder, &err).await;
                 Err(err)
             }
         }
     }
 
+    /// Same as `execute` if hedge delay is not set. Otherwise once there is no response within the delay
+    /// the same request is sent through the hedge channel. First successful response wins, the other request is cancelled
+    pub async fn execute_hedged<
+        TRequest: Clone + Send + Sync + 'static,
+        TResponse: Send + Sync + 'static,
+        TExecutor: RequestResponseGrpcExecutor<TService, TRequest, TResponse> + Send + Sync + 'static,
+    >(
+        &self,
+        request_data: TRequest,
+        grpc_executor: &TExecutor,
+    ) -> Result<TResponse, GrpcReadError> {
+        let Some(hedge_delay) = self.hedge_delay else {
+            return self.execute(request_data, grpc_executor).await;
+        };
+
+        let primary = self.execute(request_data.clone(), grpc_executor);
+
+        let hedge = || async move {
+            let _permits = self.acquire_limits().await?;
+
+            let channel = self.get_hedge_channel().await?;
+
+            self.execute_with_channel(
+                &self.hedge_channel_holder,
+                channel,
+                request_data,
+                grpc_executor,
+            )
+            .await
+        };
+
+        execute_hedged_futures(primary, hedge_delay, hedge).await
+    }
+
     pub async fn execute_with_response_as_stream<
         TRequest: Send + Sync + 'static,
         TResponse: Send + Sync + 'static,
@@ -177,7 +357,9 @@ impl<TService: Send + Sync + 'static> GrpcChannel<TService> {
         &mut self,
         request_data: TRequest,
         grpc_executor: &TExecutor,
-    ) -> Result<tonic::Streaming<TResponse>, GrpcReadError> {
+    ) -> Result<StreamedResponse<TResponse>, GrpcReadError> {
+        let permits = self.acquire_limits().await?;
+
         let service = self
             .get_service(
                 #[cfg(feature = "with-telemetry")]
@@ -198,7 +380,7 @@ impl<TService: Send + Sync + 'static> GrpcChannel<TService> {
 
         match result {
             Ok(response) => {
-                return Ok(response);
+                return Ok(self.create_streamed_response(response, permits));
             }
             Err(err) => {
                 let err = err.into();
@@ -256,6 +438,8 @@ impl<TService: Send + Sync + 'static> GrpcChannel<TService> {
         request_data: &StreamedRequest<TRequest>,
         grpc_executor: &TExecutor,
     ) -> Result<TResponse, GrpcReadError> {
+        let _permits = self.acquire_limits().await?;
+
         let service = self
             .get_service(
                 #[cfg(feature = "with-telemetry")]
@@ -295,7 +479,9 @@ impl<TService: Send + Sync + 'static> GrpcChannel<TService> {
         &mut self,
         request_data: &StreamedRequest<TRequest>,
         grpc_executor: &TExecutor,
-    ) -> Result<tonic::Streaming<TResponse>, GrpcReadError> {
+    ) -> Result<StreamedResponse<TResponse>, GrpcReadError> {
+        let permits = self.acquire_limits().await?;
+
         let service = self
             .get_service(
                 #[cfg(feature = "with-telemetry")]
@@ -316,7 +502,7 @@ impl<TService: Send + Sync + 'static> GrpcChannel<TService> {
 
         match result {
             Ok(response) => {
-                return Ok(response);
+                return Ok(self.create_streamed_response(response, permits));
             }
             Err(err) => {
                 let err = err.into();
@@ -327,6 +513,19 @@ impl<TService: Send + Sync + 'static> GrpcChannel<TService> {
     }
 }
 
+async fn drop_channel_if_needed(channel_holder: &GrpcChannelHolder, err: &GrpcReadError) -> bool {
+    let remove = match err {
+        GrpcReadError::TonicStatus(status) => status.code() == tonic::Code::Unknown,
+        _ => false,
+    };
+
+    if remove {
+        channel_holder.drop_channel(format!("{:?}", err)).await;
+    }
+
+    remove
+}
+
 #[async_trait::async_trait]
 pub trait RequestResponseGrpcExecutor<
     TService: Send + Sync + 'static,
@@ -1,4 +1,8 @@
-use std::{sync::Arc, time::Duration};
+use std::{
+    collections::HashMap,
+    sync::{Arc, RwLock},
+    time::Duration,
+};
 
 use my_logger::LogEventCtx;
 #[cfg(feature = "with-telemetry")]
@@ -7,12 +11,22 @@ use my_telemetry::MyTelemetryContext;
 use rust_extensions::UnsafeValue;
 use tokio::time::error::Elapsed;
 use tonic::transport::Channel;
+use tonic_types::{ErrorDetails, StatusExt};
 
-use crate::{GrpcChannel, GrpcChannelHolder};
+use crate::{
+    GrpcChannel, GrpcChannelHolder, GrpcClientLimit, GrpcClientLimiter, GrpcClientLimits,
+    StreamReadLimit, StreamReadLimits,
+};
 
 #[derive(Debug)]
 pub enum GrpcReadError {
     Timeout,
+    /// Total stream deadline is reached
+    DeadlineExceeded,
+    /// Stream is cancelled once collected items exceed the limit
+    LimitExceeded(StreamReadLimit),
+    /// Request is not sent because of the client side limits
+    ClientLimitExceeded(GrpcClientLimit),
     TransportError(tonic::transport::Error),
     TonicStatus(tonic::Status),
 }
@@ -20,6 +34,11 @@ pub enum GrpcReadError {
 #[async_trait::async_trait]
 pub trait GrpcClientSettings {
     async fn get_grpc_url(&self, name: &'static str) -> GrpcUrl;
+
+    /// Endpoint hedged requests are sent to. `None` - the same url through a separate connection
+    async fn get_hedge_grpc_url(&self, _name: &'static str) -> Option<GrpcUrl> {
+        None
+    }
 }
 
 #[derive(Debug)]
@@ -50,9 +69,17 @@ pub trait GrpcServiceFactory<TService: Send + Sync + 'static> {
 
 pub struct GrpcChannelPool<TService: Send + Sync + 'static> {
     pub grpc_channel_holder: Arc<GrpcChannelHolder>,
+    /// Connection used by hedged requests
+    pub hedge_channel_holder: Arc<GrpcChannelHolder>,
     pub request_timeout: Duration,
     pub ping_timeout: Duration,
     pub ping_interval: Duration,
+    pub stream_read_limits: StreamReadLimits,
+    /// Max delay of the retry requested by the server. `None` - request timeout
+    pub max_retry_delay: Option<Duration>,
+    /// Limits of all requests of the client
+    pub limiter: Arc<GrpcClientLimiter>,
+    method_limiters: RwLock<HashMap<String, Arc<GrpcClientLimiter>>>,
     get_grpc_address: Arc<dyn GrpcClientSettings + Send + Sync + 'static>,
     service_factory: Arc<dyn GrpcServiceFactory<TService> + Send + Sync + 'static>,
     #[cfg(feature = "with-ssh")]
@@ -70,9 +97,14 @@ impl<'s, TService: Send + Sync + 'static> GrpcChannelPool<TService> {
     ) -> Self {
         let result = Self {
             grpc_channel_holder: Arc::new(GrpcChannelHolder::new()),
+            hedge_channel_holder: Arc::new(GrpcChannelHolder::new()),
             request_timeout,
             ping_timeout,
             ping_interval,
+            stream_read_limits: StreamReadLimits::default(),
+            max_retry_delay: None,
+            limiter: Arc::new(GrpcClientLimiter::default()),
+            method_limiters: RwLock::new(HashMap::new()),
             get_grpc_address,
             service_factory,
             #[cfg(feature = "with-ssh")]
@@ -85,6 +117,50 @@ impl<'s, TService: Send + Sync + 'static> GrpcChannelPool<TService> {
         result
     }
 
+    /// Limits applied to all server streams of the client
+    pub fn with_stream_read_limits(mut self, stream_read_limits: StreamReadLimits) -> Self {
+        self.stream_read_limits = stream_read_limits;
+        self
+    }
+
+    pub fn with_max_retry_delay(mut self, max_retry_delay: Duration) -> Self {
+        self.max_retry_delay = Some(max_retry_delay);
+        self
+    }
+
+    pub fn with_limits(self, limits: GrpcClientLimits) -> Self {
+        self.limiter.set_limits(limits);
+        self
+    }
+
+    pub fn with_method_limits(self, fn_name: &str, limits: GrpcClientLimits) -> Self {
+        self.set_method_limits(fn_name, limits);
+        self
+    }
+
+    pub fn set_limits(&self, limits: GrpcClientLimits) {
+        self.limiter.set_limits(limits);
+    }
+
+    /// Limits applied to the rpc on top of the limits of the client. `fn_name` is the name of rpc in the proto file
+    pub fn set_method_limits(&self, fn_name: &str, limits: GrpcClientLimits) {
+        let mut method_limiters = self.method_limiters.write().unwrap();
+
+        match method_limiters.get(fn_name) {
+            Some(limiter) => limiter.set_limits(limits),
+            None => {
+                method_limiters.insert(
+                    fn_name.to_string(),
+                    Arc::new(GrpcClientLimiter::new(limits)),
+                );
+            }
+        }
+    }
+
+    pub fn get_method_limiter(&self, fn_name: &str) -> Option<Arc<GrpcClientLimiter>> {
+        self.method_limiters.read().unwrap().get(fn_name).cloned()
+    }
+
     pub fn get_channel(
         &self,
         #[cfg(feature = "with-telemetry")] ctx: &MyTelemetryContext,
@@ -99,7 +175,11 @@ impl<'s, TService: Send + Sync + 'static> GrpcChannelPool<TService> {
             ctx.clone(),
             #[cfg(feature = "with-ssh")]
             self.ssh_target.clone(),
-        );
+        )
+        .with_stream_read_limits(self.stream_read_limits)
+        .with_max_retry_delay(self.max_retry_delay)
+        .with_hedge_channel_holder(self.hedge_channel_holder.clone())
+        .with_limiter(self.limiter.clone());
     }
 
     fn ping_channel(&self) {
@@ -108,27 +188,34 @@ impl<'s, TService: Send + Sync + 'static> GrpcChannelPool<TService> {
         let enable_ping = self.enable_ping.clone();
         let ping_interval = self.ping_interval;
         let ping_timeout = self.ping_timeout;
-        let grpc_channel_holder = self.grpc_channel_holder.clone();
         let grpc_client_settings = self.get_grpc_address.clone();
         let grpc_service_factory = self.service_factory.clone();
         let request_timeout = self.request_timeout;
-        tokio::spawn(ping_loop(
-            #[cfg(feature = "with-ssh")]
-            ssh_target,
-            enable_ping,
-            ping_interval,
-            ping_timeout,
-            request_timeout,
-            grpc_channel_holder,
-            grpc_client_settings,
-            grpc_service_factory,
-        ));
+
+        for (grpc_channel_holder, hedge) in [
+            (self.grpc_channel_holder.clone(), false),
+            (self.hedge_channel_holder.clone(), true),
+        ] {
+            tokio::spawn(ping_loop(
+                #[cfg(feature = "with-ssh")]
+                ssh_target.clone(),
+                enable_ping.clone(),
+                ping_interval,
+                ping_timeout,
+                request_timeout,
+                grpc_channel_holder,
+                hedge,
+                grpc_client_settings.clone(),
+                grpc_service_factory.clone(),
+            ));
+        }
     }
 }
 
 async fn get_or_create_channel<TService: Send + Sync + 'static>(
     #[cfg(feature = "with-ssh")] ssh_target: &crate::SshTarget,
     grpc_channel_holder: &Arc<GrpcChannelHolder>,
+    hedge: bool,
     grpc_client_settings: &Arc<dyn GrpcClientSettings + Send + Sync + 'static>,
     grpc_service_factory: &Arc<dyn GrpcServiceFactory<TService> + Send + Sync + 'static>,
     request_timeout: Duration,
@@ -136,9 +223,17 @@ async fn get_or_create_channel<TService: Send + Sync + 'static>(
     match grpc_channel_holder.get().await {
         Some(channel) => Ok(channel),
         None => {
-            let grpc_url = grpc_client_settings
-                .get_grpc_url(grpc_service_factory.get_service_name())
-                .await;
+            let service_name = grpc_service_factory.get_service_name();
+
+            let hedge_grpc_url = match hedge {
+                true => grpc_client_settings.get_hedge_grpc_url(service_name).await,
+                false => None,
+            };
+
+            let grpc_url = match hedge_grpc_url {
+                Some(grpc_url) => grpc_url,
+                None => grpc_client_settings.get_grpc_url(service_name).await,
+            };
             my_logger::LOGGER.write_warning(
                 "GrpcChannel::ping_channel",
                 "Channel is not available. Creating One",
@@ -167,19 +262,33 @@ async fn ping_loop<TService: Send + Sync + 'static>(
     ping_timeout: Duration,
     request_timeout: Duration,
     grpc_channel_holder: Arc<GrpcChannelHolder>,
+    hedge: bool,
     grpc_client_settings: Arc<dyn GrpcClientSettings + Send + Sync + 'static>,
     grpc_service_factory: Arc<dyn GrpcServiceFactory<TService> + Send + Sync + 'static>,
 ) {
+    // Hedge connection is created by the first hedged request, so it is not pinged before
+    let mut is_used = !hedge;
+
     loop {
         if !enable_ping.get_value() {
             tokio::time::sleep(ping_interval).await;
             continue;
         }
 
+        if !is_used {
+            is_used = grpc_channel_holder.get().await.is_some();
+
+            if !is_used {
+                tokio::time::sleep(ping_interval).await;
+                continue;
+            }
+        }
+
         let channel = get_or_create_channel(
             #[cfg(feature = "with-ssh")]
             &ssh_target,
             &grpc_channel_holder,
+            hedge,
             &grpc_client_settings,
             &grpc_service_factory,
             request_timeout,
@@ -260,6 +369,31 @@ pub enum PingResult {
     Timeout,
 }
 
+impl GrpcReadError {
+    pub fn get_status(&self) -> Option<&tonic::Status> {
+        match self {
+            GrpcReadError::TonicStatus(status) => Some(status),
+            _ => None,
+        }
+    }
+
+    /// Details decoded from grpc-status-details-bin of the status returned by the server
+    pub fn get_error_details(&self) -> Option<ErrorDetails> {
+        let status = self.get_status()?;
+        status.check_error_details().ok()
+    }
+
+    /// Request was not sent, so there is no reason to retry it
+    pub fn is_client_limit_exceeded(&self) -> bool {
+        matches!(self, GrpcReadError::ClientLimitExceeded(_))
+    }
+
+    pub fn get_retry_delay(&self) -> Option<Duration> {
+        let status = self.get_status()?;
+        status.get_details_retry_info()?.retry_delay
+    }
+}
+
 impl From<Elapsed> for GrpcReadError {
     fn from(_: Elapsed) -> Self {
         Self::Timeout
@@ -277,3 +411,34 @@ impl From<tonic::transport::Error> for GrpcReadError {
         Self::TransportError(value)
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use std::time::Duration;
+
+    use tonic_types::{ErrorDetails, StatusExt};
+
+    use super::GrpcReadError;
+
+    #[test]
+    fn test_retry_delay_is_read_from_status_details() {
+        let status = tonic::Status::with_error_details(
+            tonic::Code::Unavailable,
+            "Try later",
+            ErrorDetails::with_retry_info(Some(Duration::from_millis(300))),
+        );
+
+        let err = GrpcReadError::from(status);
+
+        assert_eq!(err.get_retry_delay(), Some(Duration::from_millis(300)));
+    }
+
+    #[test]
+    fn test_no_error_details() {
+        let err = GrpcReadError::from(tonic::Status::internal("Error"));
+
+        assert_eq!(err.get_retry_delay(), None);
+        assert!(err.get_error_details().unwrap().bad_request().is_none());
+        assert_eq!(GrpcReadError::Timeout.get_retry_delay(), None);
+    }
+}
@@ -0,0 +1,195 @@
+use std::time::Duration;
+
+use futures_util::StreamExt;
+
+use crate::{
+    GrpcChannel, GrpcReadError, RequestWithResponseAsStreamGrpcExecutor, StreamedResponse,
+};
+
+pub const DEFAULT_SUBSCRIPTION_INITIAL_DELAY: Duration = Duration::from_millis(500);
+pub const DEFAULT_SUBSCRIPTION_MAX_DELAY: Duration = Duration::from_secs(30);
+
+#[derive(Debug, Clone, Copy)]
+pub struct GrpcSubscriptionOptions {
+    /// Delay before the first reconnect attempt. Doubled on each next attempt in a row
+    pub initial_delay: Duration,
+    pub max_delay: Duration,
+}
+
+impl Default for GrpcSubscriptionOptions {
+    fn default() -> Self {
+        Self {
+            initial_delay: DEFAULT_SUBSCRIPTION_INITIAL_DELAY,
+            max_delay: DEFAULT_SUBSCRIPTION_MAX_DELAY,
+        }
+    }
+}
+
+impl GrpcSubscriptionOptions {
+    pub fn with_initial_delay(mut self, initial_delay: Duration) -> Self {
+        self.initial_delay = initial_delay;
+        self
+    }
+
+    pub fn with_max_delay(mut self, max_delay: Duration) -> Self {
+        self.max_delay = max_delay;
+        self
+    }
+
+    pub fn get_delay(&self, attempt_no: usize) -> Duration {
+        let power = attempt_no.saturating_sub(1).min(16) as u32;
+        self.initial_delay
+            .saturating_mul(2u32.pow(power))
+            .min(self.max_delay)
+    }
+}
+
+#[derive(Debug)]
+pub enum SubscriptionEvent<TItem> {
+    /// Stream is opened. Items sent by the server while there was no connection are missed
+    Connected,
+    Item(TItem),
+    /// Stream is broken. `None` - server finished the stream
+    Gap(Option<GrpcReadError>),
+    /// Next connect attempt is made after the delay. Error is the reason the previous attempt failed
+    Reconnecting {
+        attempt_no: usize,
+        delay: Duration,
+        error: Option<GrpcReadError>,
+    },
+}
+
+enum SubscriptionState<TItem> {
+    Connect(Option<Duration>),
+    Connected(StreamedResponse<TItem>),
+    Disconnected,
+}
+
+/// Keeps the server stream open forever and resubscribes with backoff once it is broken.
+/// Nothing is running in the background - subscription is stopped once it is dropped.
+pub struct GrpcSubscription<
+    's,
+    TService: Send + Sync + 'static,
+    TRequest: Clone + Send + Sync + 'static,
+    TResponse: Send + Sync + 'static,
+    TExecutor: RequestWithResponseAsStreamGrpcExecutor<TService, TRequest, TResponse> + Send + Sync + 'static,
+> {
+    channel: GrpcChannel<TService>,
+    request: TRequest,
+    grpc_executor: &'s TExecutor,
+    options: GrpcSubscriptionOptions,
+    state: SubscriptionState<TResponse>,
+    attempt_no: usize,
+}
+
+impl<
+        's,
+        TService: Send + Sync + 'static,
+        TRequest: Clone + Send + Sync + 'static,
+        TResponse: Send + Sync + 'static,
+        TExecutor: RequestWithResponseAsStreamGrpcExecutor<TService, TRequest, TResponse>
+            + Send
+            + Sync
+            + 'static,
+    > GrpcSubscription<'s, TService, TRequest, TResponse, TExecutor>
+{
+    pub fn new(
+        channel: GrpcChannel<TService>,
+        request: TRequest,
+        grpc_executor: &'s TExecutor,
+    ) -> Self {
+        Self {
+            channel,
+            request,
+            grpc_executor,
+            options: GrpcSubscriptionOptions::default(),
+            state: SubscriptionState::Connect(None),
+            attempt_no: 0,
+        }
+    }
+
+    pub fn with_options(mut self, options: GrpcSubscriptionOptions) -> Self {
+        self.options = options;
+        self
+    }
+
+    pub fn is_connected(&self) -> bool {
+        matches!(self.state, SubscriptionState::Connected(_))
+    }
+
+    /// Subscription never ends, so there is always the next event
+    pub async fn next_event(&mut self) -> SubscriptionEvent<TResponse> {
+        match &mut self.state {
+            SubscriptionState::Connected(stream) => {
+                let gap = match stream.next().await {
+                    Some(Ok(item)) => return SubscriptionEvent::Item(item),
+                    Some(Err(err)) => Some(err),
+                    None => None,
+                };
+
+                self.state = SubscriptionState::Disconnected;
+                SubscriptionEvent::Gap(gap)
+            }
+            SubscriptionState::Disconnected => self.schedule_reconnect(None),
+            SubscriptionState::Connect(delay) => {
+                if let Some(delay) = *delay {
+                    tokio::time::sleep(delay).await;
+                }
+
+                let result = self
+                    .channel
+                    .execute_with_response_as_stream(self.request.clone(), self.grpc_executor)
+                    .await;
+
+                match result {
+                    Ok(stream) => {
+                        self.state = SubscriptionState::Connected(stream);
+                        self.attempt_no = 0;
+                        SubscriptionEvent::Connected
+                    }
+                    Err(err) => self.schedule_reconnect(Some(err)),
+                }
+            }
+        }
+    }
+
+    fn schedule_reconnect(&mut self, error: Option<GrpcReadError>) -> SubscriptionEvent<TResponse> {
+        self.attempt_no += 1;
+
+        let delay = match error
+            .as_ref()
+            .and_then(|err| self.channel.get_retry_delay(err))
+        {
+            Some(retry_delay) => retry_delay,
+            None => self.options.get_delay(self.attempt_no),
+        };
+
+        self.state = SubscriptionState::Connect(Some(delay));
+
+        SubscriptionEvent::Reconnecting {
+            attempt_no: self.attempt_no,
+            delay,
+            error,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::Duration;
+
+    use super::GrpcSubscriptionOptions;
+
+    #[test]
+    fn test_reconnect_delay_is_doubled_up_to_max() {
+        let options = GrpcSubscriptionOptions::default()
+            .with_initial_delay(Duration::from_millis(100))
+            .with_max_delay(Duration::from_secs(1));
+
+        assert_eq!(options.get_delay(1), Duration::from_millis(100));
+        assert_eq!(options.get_delay(2), Duration::from_millis(200));
+        assert_eq!(options.get_delay(4), Duration::from_millis(800));
+        assert_eq!(options.get_delay(5), Duration::from_secs(1));
+        assert_eq!(options.get_delay(1000), Duration::from_secs(1));
+    }
+}
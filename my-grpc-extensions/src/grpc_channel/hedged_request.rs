@@ -0,0 +1,95 @@
+use std::{future::Future, pin::pin, time::Duration};
+
+use futures::future::{select, Either};
+
+use crate::GrpcReadError;
+
+/// Hedge request is started only if primary one is not finished within the delay.
+/// If one of the requests fails - the result of the other one is awaited
+pub(crate) async fn execute_hedged_futures<
+    TResponse,
+    TPrimary: Future<Output = Result<TResponse, GrpcReadError>>,
+    THedge: Future<Output = Result<TResponse, GrpcReadError>>,
+>(
+    primary: TPrimary,
+    hedge_delay: Duration,
+    hedge: impl FnOnce() -> THedge,
+) -> Result<TResponse, GrpcReadError> {
+    let primary = pin!(primary);
+    let delay = pin!(tokio::time::sleep(hedge_delay));
+
+    let primary = match select(primary, delay).await {
+        Either::Left((result, _)) => return result,
+        Either::Right((_, primary)) => primary,
+    };
+
+    let hedge = pin!(hedge());
+
+    match select(primary, hedge).await {
+        Either::Left((Ok(response), _)) => Ok(response),
+        Either::Left((Err(_), hedge)) => hedge.await,
+        Either::Right((Ok(response), _)) => Ok(response),
+        Either::Right((Err(_), primary)) => primary.await,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{
+        sync::atomic::{AtomicBool, Ordering},
+        time::Duration,
+    };
+
+    use super::*;
+
+    async fn respond(
+        delay_ms: u64,
+        result: Result<u64, GrpcReadError>,
+    ) -> Result<u64, GrpcReadError> {
+        tokio::time::sleep(Duration::from_millis(delay_ms)).await;
+        result
+    }
+
+    #[test]
+    fn test_hedged_requests() {
+        let rt = tokio::runtime::Builder::new_current_thread()
+            .enable_time()
+            .build()
+            .unwrap();
+
+        rt.block_on(async {
+            let hedge_started = AtomicBool::new(false);
+
+            let result =
+                execute_hedged_futures(respond(1, Ok(1)), Duration::from_millis(50), || {
+                    hedge_started.store(true, Ordering::SeqCst);
+                    respond(1, Ok(2))
+                })
+                .await;
+            assert_eq!(result.unwrap(), 1);
+            assert!(!hedge_started.load(Ordering::SeqCst));
+
+            let result =
+                execute_hedged_futures(respond(200, Ok(1)), Duration::from_millis(10), || {
+                    respond(10, Ok(2))
+                })
+                .await;
+            assert_eq!(result.unwrap(), 2);
+
+            let result =
+                execute_hedged_futures(respond(100, Ok(1)), Duration::from_millis(10), || {
+                    respond(10, Err(GrpcReadError::Timeout))
+                })
+                .await;
+            assert_eq!(result.unwrap(), 1);
+
+            let result = execute_hedged_futures(
+                respond(1, Err(GrpcReadError::Timeout)),
+                Duration::from_millis(50),
+                || respond(1, Ok(2)),
+            )
+            .await;
+            assert!(result.is_err());
+        });
+    }
+}
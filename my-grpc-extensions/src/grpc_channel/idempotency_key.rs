@@ -0,0 +1,71 @@
+pub const IDEMPOTENCY_KEY_HEADER: &str = "x-idempotency-key";
+
+tokio::task_local! {
+    static IDEMPOTENCY_KEY: String;
+}
+
+/// Random uuid v4, so the key of another client can not be guessed
+pub fn generate_idempotency_key() -> String {
+    let mut bytes = [0u8; 16];
+    getrandom::fill(&mut bytes).expect("OS random source is not available");
+
+    bytes[6] = (bytes[6] & 0x0f) | 0x40;
+    bytes[8] = (bytes[8] & 0x3f) | 0x80;
+
+    let hex: String = bytes.iter().map(|itm| format!("{:02x}", itm)).collect();
+
+    format!(
+        "{}-{}-{}-{}-{}",
+        &hex[0..8],
+        &hex[8..12],
+        &hex[12..16],
+        &hex[16..20],
+        &hex[20..32]
+    )
+}
+
+/// Every request built inside the future by generated client gets the same idempotency key header
+pub async fn with_idempotency_key<TResult>(
+    idempotency_key: String,
+    future: impl std::future::Future<Output = TResult>,
+) -> TResult {
+    IDEMPOTENCY_KEY.scope(idempotency_key, future).await
+}
+
+pub fn apply_idempotency_key<T>(request: &mut tonic::Request<T>) {
+    let idempotency_key = IDEMPOTENCY_KEY.try_with(|itm| itm.clone());
+
+    if let Ok(idempotency_key) = idempotency_key {
+        if let Ok(value) = tonic::metadata::MetadataValue::try_from(idempotency_key.as_str()) {
+            request.metadata_mut().insert(IDEMPOTENCY_KEY_HEADER, value);
+        }
+    }
+}
+
+pub fn get_idempotency_key<T>(request: &tonic::Request<T>) -> Option<String> {
+    let value = request.metadata().get(IDEMPOTENCY_KEY_HEADER)?;
+    let value = value.to_str().ok()?;
+    Some(value.to_string())
+}
+
+#[cfg(test)]
+mod tests {
+
+    #[test]
+    fn test_idempotency_keys_are_unique() {
+        let key1 = super::generate_idempotency_key();
+        let key2 = super::generate_idempotency_key();
+
+        assert_ne!(key1, key2);
+        assert_eq!(key1.len(), 36);
+        assert_eq!(&key1[14..15], "4");
+    }
+
+    #[test]
+    fn test_idempotency_key_is_not_applied_outside_of_scope() {
+        let mut request = tonic::Request::new(());
+        super::apply_idempotency_key(&mut request);
+
+        assert_eq!(super::get_idempotency_key(&request), None);
+    }
+}
@@ -1,27 +1,36 @@
+mod bidi_stream;
 mod grpc_channel;
 mod grpc_channel_holder;
 mod grpc_channel_pool;
+mod grpc_subscription;
+mod hedged_request;
 #[cfg(feature = "with-ssh")]
 mod port_forwards_pool;
 mod request_builder;
 mod request_builder_with_input_stream;
 mod request_builder_with_input_stream_with_retires;
 mod request_builder_with_retries;
+mod resumable_streamed_response;
 mod streamed_response;
+pub use bidi_stream::*;
 pub use grpc_channel::*;
 pub use grpc_channel_holder::*;
 pub use grpc_channel_pool::*;
+pub use grpc_subscription::*;
 #[cfg(feature = "with-ssh")]
 pub use port_forwards_pool::*;
 pub use request_builder::*;
 pub use request_builder_with_input_stream::*;
 pub use request_builder_with_input_stream_with_retires::*;
 pub use request_builder_with_retries::*;
+pub use resumable_streamed_response::*;
 pub use streamed_response::*;
 mod grpc_connect_url;
 pub use grpc_connect_url::*;
 mod streamed_request;
 pub use streamed_request::*;
+mod idempotency_key;
+pub use idempotency_key::*;
 
 #[cfg(feature = "with-tls")]
 fn extract_domain_name(src: &str) -> &str {
@@ -1,6 +1,6 @@
 use crate::{
-    GrpcReadError, GrpcChannel, RequestBuilderWithRetries, RequestResponseGrpcExecutor,
-    RequestWithResponseAsStreamGrpcExecutor, StreamedResponse,
+    GrpcReadError, GrpcChannel, GrpcSubscription, RequestBuilderWithRetries,
+    RequestResponseGrpcExecutor, RequestWithResponseAsStreamGrpcExecutor, StreamedResponse,
 };
 
 pub struct RequestBuilder<TService: Send + Sync + 'static, TRequest: Clone + Send + Sync + 'static>
@@ -30,14 +30,14 @@ impl<TService: Send + Sync + 'static, TRequest: Clone + Send + Sync + 'static>
         TResponse,
         TExecutor: RequestResponseGrpcExecutor<TService, TRequest, TResponse> + Send + Sync + 'static,
     >(
-        mut self,
+        self,
         grpc_executor: &TExecutor,
     ) -> Result<TResponse, GrpcReadError>
     where
         TResponse: Send + Sync + 'static,
     {
         self.channel
-            .execute(self.input_contract, grpc_executor)
+            .execute_hedged(self.input_contract, grpc_executor)
             .await
     }
 
@@ -59,6 +59,21 @@ impl<TService: Send + Sync + 'static, TRequest: Clone + Send + Sync + 'static>
             .execute_with_response_as_stream(self.input_contract.clone(), grpc_executor)
             .await?;
 
-        return Ok(StreamedResponse::new(result, self.channel.request_timeout));
+        return Ok(result);
+    }
+
+    /// Stream is opened on the first event read
+    pub fn subscribe<
+        's,
+        TResponse: Send + Sync + 'static,
+        TExecutor: RequestWithResponseAsStreamGrpcExecutor<TService, TRequest, TResponse>
+            + Send
+            + Sync
+            + 'static,
+    >(
+        self,
+        grpc_executor: &'s TExecutor,
+    ) -> GrpcSubscription<'s, TService, TRequest, TResponse, TExecutor> {
+        GrpcSubscription::new(self.channel, self.input_contract, grpc_executor)
     }
 }
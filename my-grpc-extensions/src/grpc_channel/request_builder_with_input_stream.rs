@@ -63,14 +63,8 @@ impl<TService: Send + Sync + 'static, TRequest: Clone + Send + Sync + 'static>
     where
         TResponse: Send + Sync + 'static,
     {
-        let stream_to_read = self
-            .channel
+        self.channel
             .execute_input_as_stream_response_as_stream(&self.input_contract, grpc_executor)
-            .await?;
-
-        Ok(StreamedResponse::new(
-            stream_to_read,
-            self.channel.request_timeout,
-        ))
+            .await
     }
 }
@@ -50,9 +50,16 @@ impl<TService: Send + Sync + 'static, TRequest: Clone + Send + Sync + 'static>
                 Ok(response) => return Ok(response),
                 Err(err) => {
                     attempt_no += 1;
-                    if attempt_no >= self.max_attempts_amount {
+                    if attempt_no >= self.max_attempts_amount
+                        || !self.input_contract.is_replayable()
+                        || err.is_client_limit_exceeded()
+                    {
                         return Err(err);
                     }
+
+                    if let Some(retry_delay) = self.channel.get_retry_delay(&err) {
+                        tokio::time::sleep(retry_delay).await;
+                    }
                 }
             }
         }
@@ -80,16 +87,20 @@ impl<TService: Send + Sync + 'static, TRequest: Clone + Send + Sync + 'static>
 
             match result {
                 Ok(stream_to_read) => {
-                    return Ok(StreamedResponse::new(
-                        stream_to_read,
-                        self.channel.request_timeout,
-                    ));
+                    return Ok(stream_to_read);
                 }
                 Err(err) => {
                     attempt_no += 1;
-                    if attempt_no >= self.max_attempts_amount {
+                    if attempt_no >= self.max_attempts_amount
+                        || !self.input_contract.is_replayable()
+                        || err.is_client_limit_exceeded()
+                    {
                         return Err(err);
                     }
+
+                    if let Some(retry_delay) = self.channel.get_retry_delay(&err) {
+                        tokio::time::sleep(retry_delay).await;
+                    }
                 }
             }
         }
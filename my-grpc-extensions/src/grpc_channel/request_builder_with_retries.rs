@@ -1,6 +1,7 @@
 use crate::{
-    GrpcChannel, GrpcReadError, RequestResponseGrpcExecutor,
-    RequestWithResponseAsStreamGrpcExecutor, StreamedResponse,
+    generate_idempotency_key, with_idempotency_key, GrpcChannel, GrpcReadError,
+    RequestResponseGrpcExecutor, RequestWithResponseAsStreamGrpcExecutor, ResumableStreamItem,
+    ResumableStreamRequest, ResumableStreamedResponse, StreamedResponse,
 };
 
 pub struct RequestBuilderWithRetries<
@@ -31,29 +32,39 @@ impl<TService: Send + Sync + 'static, TRequest: Clone + Send + Sync + 'static>
         TResponse,
         TExecutor: RequestResponseGrpcExecutor<TService, TRequest, TResponse> + Send + Sync + 'static,
     >(
-        mut self,
+        self,
         grpc_executor: &TExecutor,
     ) -> Result<TResponse, GrpcReadError>
     where
         TResponse: Send + Sync + 'static,
     {
-        let mut attempt_no = 0;
-        loop {
-            let result = self
-                .channel
-                .execute(self.input_contract.clone(), grpc_executor)
-                .await;
+        let idempotency_key = generate_idempotency_key();
 
-            match result {
-                Ok(response) => return Ok(response),
-                Err(err) => {
-                    attempt_no += 1;
-                    if attempt_no >= self.max_attempts_amount {
-                        return Err(err);
+        with_idempotency_key(idempotency_key, async move {
+            let mut attempt_no = 0;
+            loop {
+                let result = self
+                    .channel
+                    .execute_hedged(self.input_contract.clone(), grpc_executor)
+                    .await;
+
+                match result {
+                    Ok(response) => return Ok(response),
+                    Err(err) => {
+                        attempt_no += 1;
+                        if attempt_no >= self.max_attempts_amount || err.is_client_limit_exceeded()
+                        {
+                            return Err(err);
+                        }
+
+                        if let Some(retry_delay) = self.channel.get_retry_delay(&err) {
+                            tokio::time::sleep(retry_delay).await;
+                        }
                     }
                 }
             }
-        }
+        })
+        .await
     }
 
     pub async fn get_streamed_response<
@@ -66,6 +77,22 @@ impl<TService: Send + Sync + 'static, TRequest: Clone + Send + Sync + 'static>
         mut self,
         grpc_executor: &TExecutor,
     ) -> Result<StreamedResponse<TResponse>, GrpcReadError>
+    where
+        TResponse: Send + Sync + 'static,
+    {
+        self.connect_to_stream(grpc_executor).await
+    }
+
+    async fn connect_to_stream<
+        TResponse,
+        TExecutor: RequestWithResponseAsStreamGrpcExecutor<TService, TRequest, TResponse>
+            + Send
+            + Sync
+            + 'static,
+    >(
+        &mut self,
+        grpc_executor: &TExecutor,
+    ) -> Result<StreamedResponse<TResponse>, GrpcReadError>
     where
         TResponse: Send + Sync + 'static,
     {
@@ -78,18 +105,48 @@ impl<TService: Send + Sync + 'static, TRequest: Clone + Send + Sync + 'static>
 
             match result {
                 Ok(stream_to_read) => {
-                    return Ok(StreamedResponse::new(
-                        stream_to_read,
-                        self.channel.request_timeout,
-                    ));
+                    return Ok(stream_to_read);
                 }
                 Err(err) => {
                     attempt_no += 1;
-                    if attempt_no >= self.max_attempts_amount {
+                    if attempt_no >= self.max_attempts_amount || err.is_client_limit_exceeded() {
                         return Err(err);
                     }
+
+                    if let Some(retry_delay) = self.channel.get_retry_delay(&err) {
+                        tokio::time::sleep(retry_delay).await;
+                    }
                 }
             }
         }
     }
+
+    /// Stream is resumed from the token of the last received item once it is broken while reading
+    pub async fn get_resumable_streamed_response<
+        's,
+        TResponse: ResumableStreamItem<TRequest::ResumeToken> + Send + Sync + 'static,
+        TExecutor: RequestWithResponseAsStreamGrpcExecutor<TService, TRequest, TResponse>
+            + Send
+            + Sync
+            + 'static,
+    >(
+        mut self,
+        grpc_executor: &'s TExecutor,
+    ) -> Result<
+        ResumableStreamedResponse<'s, TService, TRequest, TResponse, TExecutor>,
+        GrpcReadError,
+    >
+    where
+        TRequest: ResumableStreamRequest,
+    {
+        let stream = self.connect_to_stream(grpc_executor).await?;
+
+        Ok(ResumableStreamedResponse::new(
+            self.channel,
+            self.input_contract,
+            grpc_executor,
+            stream,
+            self.max_attempts_amount,
+        ))
+    }
 }
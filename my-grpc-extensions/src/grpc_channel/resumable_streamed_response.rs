@@ -0,0 +1,158 @@
+use futures_util::StreamExt;
+
+use crate::{
+    GrpcChannel, GrpcReadError, RequestWithResponseAsStreamGrpcExecutor, StreamReadCounter,
+    StreamedResponse,
+};
+
+/// Request contract of the server stream which can be continued from the resume token
+pub trait ResumableStreamRequest: Clone {
+    type ResumeToken: Clone + Send + Sync + 'static;
+    fn set_resume_token(&mut self, resume_token: Self::ResumeToken);
+}
+
+/// Item of the server stream which carries sequence or offset token
+pub trait ResumableStreamItem<TResumeToken> {
+    fn get_resume_token(&self) -> Option<TResumeToken>;
+}
+
+/// Reconnects transparently once the stream is broken and re-issues the request
+/// with the resume token of the last received item.
+pub struct ResumableStreamedResponse<
+    's,
+    TService: Send + Sync + 'static,
+    TRequest: ResumableStreamRequest + Send + Sync + 'static,
+    TResponse: ResumableStreamItem<TRequest::ResumeToken> + Send + Sync + 'static,
+    TExecutor: RequestWithResponseAsStreamGrpcExecutor<TService, TRequest, TResponse> + Send + Sync + 'static,
+> {
+    channel: GrpcChannel<TService>,
+    request: TRequest,
+    grpc_executor: &'s TExecutor,
+    stream: StreamedResponse<TResponse>,
+    last_resume_token: Option<TRequest::ResumeToken>,
+    max_resume_attempts: usize,
+    resume_attempt_no: usize,
+}
+
+impl<
+        's,
+        TService: Send + Sync + 'static,
+        TRequest: ResumableStreamRequest + Send + Sync + 'static,
+        TResponse: ResumableStreamItem<TRequest::ResumeToken> + Send + Sync + 'static,
+        TExecutor: RequestWithResponseAsStreamGrpcExecutor<TService, TRequest, TResponse>
+            + Send
+            + Sync
+            + 'static,
+    > ResumableStreamedResponse<'s, TService, TRequest, TResponse, TExecutor>
+{
+    pub fn new(
+        channel: GrpcChannel<TService>,
+        request: TRequest,
+        grpc_executor: &'s TExecutor,
+        stream: StreamedResponse<TResponse>,
+        max_resume_attempts: usize,
+    ) -> Self {
+        Self {
+            channel,
+            request,
+            grpc_executor,
+            stream,
+            last_resume_token: None,
+            max_resume_attempts,
+            resume_attempt_no: 0,
+        }
+    }
+
+    pub fn get_last_resume_token(&self) -> Option<&TRequest::ResumeToken> {
+        self.last_resume_token.as_ref()
+    }
+
+    pub async fn get_next_item(&mut self) -> Option<Result<TResponse, GrpcReadError>> {
+        loop {
+            let err = match self.stream.next().await? {
+                Ok(item) => {
+                    if let Some(resume_token) = item.get_resume_token() {
+                        self.last_resume_token = Some(resume_token);
+                    }
+
+                    self.resume_attempt_no = 0;
+                    return Some(Ok(item));
+                }
+                Err(err) => err,
+            };
+
+            if let Err(err) = self.resume(err).await {
+                return Some(Err(err));
+            }
+        }
+    }
+
+    /// Applies all the read limits
+    pub async fn into_vec<TResult: From<TResponse>>(mut self) -> Result<Vec<TResult>, GrpcReadError>
+    where
+        TResponse: prost::Message,
+    {
+        let mut counter = StreamReadCounter::new(self.stream.get_read_limits());
+        let mut result = Vec::new();
+
+        while let Some(item) = self.get_next_item().await {
+            let item = item?;
+            counter.add(&item)?;
+            result.push(item.into());
+        }
+
+        Ok(result)
+    }
+
+    async fn resume(&mut self, mut err: GrpcReadError) -> Result<(), GrpcReadError> {
+        loop {
+            if !is_resumable(&err) || self.resume_attempt_no >= self.max_resume_attempts {
+                return Err(err);
+            }
+
+            self.resume_attempt_no += 1;
+
+            if let Some(retry_delay) = self.channel.get_retry_delay(&err) {
+                tokio::time::sleep(retry_delay).await;
+            }
+
+            let mut request = self.request.clone();
+
+            if let Some(resume_token) = self.last_resume_token.as_ref() {
+                request.set_resume_token(resume_token.clone());
+            }
+
+            let result = self
+                .channel
+                .execute_with_response_as_stream(request, self.grpc_executor)
+                .await;
+
+            match result {
+                Ok(stream) => {
+                    self.stream.replace_stream(stream);
+                    return Ok(());
+                }
+                Err(next_err) => err = next_err,
+            }
+        }
+    }
+}
+
+fn is_resumable(err: &GrpcReadError) -> bool {
+    match err {
+        GrpcReadError::Timeout => true,
+        GrpcReadError::DeadlineExceeded => false,
+        GrpcReadError::LimitExceeded(_) => false,
+        GrpcReadError::ClientLimitExceeded(_) => false,
+        GrpcReadError::TransportError(_) => true,
+        GrpcReadError::TonicStatus(status) => match status.code() {
+            tonic::Code::Unavailable
+            | tonic::Code::Aborted
+            | tonic::Code::Unknown
+            | tonic::Code::Internal
+            | tonic::Code::Cancelled
+            | tonic::Code::DeadlineExceeded => true,
+            _ => false,
+        },
+    }
+}
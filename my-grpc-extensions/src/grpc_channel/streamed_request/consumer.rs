@@ -1,31 +1,14 @@
-use std::{
-    sync::{atomic::AtomicUsize, Arc},
-    time::Duration,
-};
-
-use tokio_stream::wrappers::ReceiverStream;
+use std::sync::Arc;
 
 use super::StreamedRequestInner;
 
-// Producer
-pub struct StreamedRequestConsumer<TItem: Clone> {
+pub(crate) fn create_consumer_stream<TItem: Clone + Send + Sync + 'static>(
     inner: Arc<StreamedRequestInner<TItem>>,
+) -> impl futures::Stream<Item = TItem> + Send + 'static {
+    let consumer_generation = inner.start_consumer();
 
-    index: AtomicUsize,
-}
-
-impl<TItem: Clone> StreamedRequestConsumer<TItem> {
-    pub fn new(inner: Arc<StreamedRequestInner<TItem>>) -> Self {
-        let mut stream = ReceiverStream::new(rx);
-        Self {
-            inner,
-            index: AtomicUsize::new(0),
-        }
-    }
-    pub async fn get_next(&self) -> Option<TItem> {
-        let next_index = self
-            .index
-            .fetch_add(1, std::sync::atomic::Ordering::Relaxed);
-        self.inner.receive(next_index).await
-    }
+    futures::stream::unfold((inner, 0), move |(inner, index)| async move {
+        let item = inner.get_next(consumer_generation, index).await?;
+        Some((item, (inner, index + 1)))
+    })
 }
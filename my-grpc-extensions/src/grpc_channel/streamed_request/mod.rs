@@ -2,5 +2,5 @@ mod streamed_request_inner;
 pub use streamed_request_inner::*;
 mod producer;
 pub use producer::*;
-//mod consumer;
-//pub use consumer::*;
+mod consumer;
+pub(crate) use consumer::*;
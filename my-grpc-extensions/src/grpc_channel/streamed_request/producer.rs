@@ -1,23 +1,59 @@
-use std::sync::Arc;
+use tokio::sync::{mpsc::Sender, watch};
 
-use super::StreamedRequestInner;
+use super::StreamedRequestError;
 
-// Producer
+/// Producer of the items of the client stream. Stream is finished once all the producers are dropped
+#[derive(Clone)]
 pub struct StreamedRequestProducer<TItem: Send + Sync + 'static + Clone> {
-    pub(crate) inner: Arc<StreamedRequestInner<TItem>>,
+    sender: Option<Sender<TItem>>,
+    eof_delivered: Option<watch::Receiver<bool>>,
 }
 
 impl<TItem: Send + Sync + 'static + Clone> StreamedRequestProducer<TItem> {
-    pub async fn send(&self, item: TItem) {
-        self.inner.send(item).await;
+    pub(crate) fn new(
+        sender: Option<Sender<TItem>>,
+        eof_delivered: Option<watch::Receiver<bool>>,
+    ) -> Self {
+        Self {
+            sender,
+            eof_delivered,
+        }
     }
-}
 
-impl<TItem: Send + Sync + 'static + Clone> Drop for StreamedRequestProducer<TItem> {
-    fn drop(&mut self) {
-        let inner = self.inner.clone();
-        tokio::spawn(async move {
-            inner.send_eof().await;
-        });
+    /// Waits while the buffer of the request is full
+    pub async fn send(&self, item: TItem) -> Result<(), StreamedRequestError> {
+        let Some(sender) = self.sender.as_ref() else {
+            return Err(StreamedRequestError::Closed);
+        };
+
+        sender
+            .send(item)
+            .await
+            .map_err(|_| StreamedRequestError::Closed)
+    }
+
+    pub fn is_closed(&self) -> bool {
+        match self.sender.as_ref() {
+            Some(sender) => sender.is_closed(),
+            None => true,
+        }
+    }
+
+    /// Finishes the stream and waits until all items are taken by the transport.
+    /// If there are clones of the producer - waits until they are dropped as well
+    pub async fn finish(mut self) -> Result<(), StreamedRequestError> {
+        self.sender.take();
+
+        let Some(mut eof_delivered) = self.eof_delivered.take() else {
+            return Err(StreamedRequestError::Closed);
+        };
+
+        let delivered = eof_delivered.wait_for(|delivered| *delivered).await.is_ok();
+
+        if delivered {
+            Ok(())
+        } else {
+            Err(StreamedRequestError::Closed)
+        }
     }
 }
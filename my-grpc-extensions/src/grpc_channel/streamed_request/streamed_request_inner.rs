@@ -1,122 +1,155 @@
-use tokio::sync::Mutex;
-
-pub enum RequestAsStream<TItem: Clone> {
-    NotInitialized {
-        items: Vec<TItem>,
-        has_end_of_stream: bool,
-    },
-    Initialized(Option<tokio::sync::mpsc::Sender<TItem>>),
+use std::sync::{
+    atomic::{AtomicUsize, Ordering},
+    Mutex,
+};
+
+use tokio::sync::{mpsc::Receiver, watch};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum StreamedRequestRetryPolicy {
+    /// Request is not retried once at least one item is taken by the transport
+    NoRetry,
+    /// Up to `max_items` items are kept to be sent again on retry.
+    /// Request is not retried once more items are sent
+    Replay { max_items: usize },
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum StreamedRequestError {
+    /// Request is finished or cancelled - nobody is going to read items anymore
+    Closed,
+    /// Item is not taken by the transport within the send timeout
+    Timeout,
+}
+
+struct ReplayBuffer<TItem> {
+    items: Vec<TItem>,
+    consumed: usize,
+    overflow: bool,
+}
+
+pub struct RequestAsStream<TItem: Clone> {
+    channel_size: usize,
+    receiver: tokio::sync::Mutex<Receiver<TItem>>,
+    replay_buffer: Mutex<ReplayBuffer<TItem>>,
+    retry_policy: StreamedRequestRetryPolicy,
+    eof_delivered: watch::Sender<bool>,
 }
 
-impl<TItem: Clone> Default for RequestAsStream<TItem> {
-    fn default() -> Self {
-        Self::NotInitialized {
-            items: vec![],
-            has_end_of_stream: false,
+impl<TItem: Clone> RequestAsStream<TItem> {
+    pub fn new(receiver: Receiver<TItem>, retry_policy: StreamedRequestRetryPolicy) -> Self {
+        Self {
+            channel_size: receiver.max_capacity(),
+            receiver: tokio::sync::Mutex::new(receiver),
+            replay_buffer: Mutex::new(ReplayBuffer {
+                items: Vec::new(),
+                consumed: 0,
+                overflow: false,
+            }),
+            retry_policy,
+            eof_delivered: watch::Sender::new(false),
         }
     }
+
+    pub fn get_channel_size(&self) -> usize {
+        self.channel_size
+    }
+
+    pub fn subscribe_to_eof(&self) -> watch::Receiver<bool> {
+        self.eof_delivered.subscribe()
+    }
+
+    fn get_replayed(&self, index: usize) -> Option<TItem> {
+        let replay_buffer = self.replay_buffer.lock().unwrap();
+        replay_buffer.items.get(index).cloned()
+    }
 }
 
-pub enum StreamedRequestInner<TItem: Clone> {
+pub enum StreamedRequestItems<TItem: Clone> {
     AsVec(Vec<TItem>),
-    AsStream(Mutex<RequestAsStream<TItem>>),
+    AsStream(RequestAsStream<TItem>),
+}
+
+pub struct StreamedRequestInner<TItem: Clone> {
+    items: StreamedRequestItems<TItem>,
+    consumer_generation: AtomicUsize,
 }
 
 impl<TItem: Clone> StreamedRequestInner<TItem> {
-    pub async fn send(&self, item: TItem) {
-        match self {
-            StreamedRequestInner::AsVec(_) => {
-                panic!("Can not enqueue new item to send to GRPC mode since it in Vector Mode");
-            }
-            StreamedRequestInner::AsStream(inner) => {
-                let mut write_access = inner.lock().await;
-
-                match &mut *write_access {
-                    RequestAsStream::NotInitialized {
-                        items,
-                        has_end_of_stream,
-                    } => {
-                        if *has_end_of_stream {
-                            println!("StreamedRequestInner is ended");
-                            panic!("StreamedRequestInner is ended");
-                        }
-                        items.push(item);
-                    }
-                    RequestAsStream::Initialized(sender) => match sender {
-                        Some(sender) => {
-                            let err = sender.send(item).await;
-
-                            if let Err(err) = err {
-                                println!("Can not send grpc item to stream #1. Err: {}", err);
-                                return;
-                            }
-                        }
-                        None => {
-                            println!("StreamedRequestInner is ended");
-                            panic!("StreamedRequestInner is ended");
-                        }
-                    },
-                }
-            }
+    pub fn new(items: StreamedRequestItems<TItem>) -> Self {
+        Self {
+            items,
+            consumer_generation: AtomicUsize::new(0),
         }
     }
 
-    pub async fn set_sender(&self, sender: tokio::sync::mpsc::Sender<TItem>) {
-        match self {
-            StreamedRequestInner::AsVec(items) => {
-                for itm in items {
-                    let err = sender.send(itm.clone()).await;
+    pub fn as_stream(&self) -> Option<&RequestAsStream<TItem>> {
+        match &self.items {
+            StreamedRequestItems::AsVec(_) => None,
+            StreamedRequestItems::AsStream(inner) => Some(inner),
+        }
+    }
 
-                    if let Err(err) = err {
-                        println!("Can not send grpc item to stream #1. Err: {}", err);
-                        return;
-                    }
+    /// Whether the request can be sent again from the very first item
+    pub fn is_replayable(&self) -> bool {
+        match &self.items {
+            StreamedRequestItems::AsVec(_) => true,
+            StreamedRequestItems::AsStream(inner) => {
+                let replay_buffer = inner.replay_buffer.lock().unwrap();
+                match inner.retry_policy {
+                    StreamedRequestRetryPolicy::NoRetry => replay_buffer.consumed == 0,
+                    StreamedRequestRetryPolicy::Replay { .. } => !replay_buffer.overflow,
                 }
             }
-            StreamedRequestInner::AsStream(mutex) => {
-                let mut write_access = mutex.lock().await;
-
-                match &mut *write_access {
-                    RequestAsStream::NotInitialized {
-                        items,
-                        has_end_of_stream,
-                    } => {
-                        for itm in items.drain(..) {
-                            let err = sender.send(itm.clone()).await;
-                            if let Err(err) = err {
-                                println!("Can not send grpc item to stream #2. Err: {}", err);
-                                return;
-                            }
-                        }
-                        if *has_end_of_stream {
-                            return;
-                        }
-                    }
-                    RequestAsStream::Initialized(existing_sender) => {
-                        // Allow re-binding sender for retries; drop old sender if present
-                        *existing_sender = None;
-                    }
-                }
-
-                *write_access = RequestAsStream::Initialized(Some(sender));
-            }
         }
     }
 
-    pub async fn send_eof(&self) {
-        match self {
-            StreamedRequestInner::AsVec(_) => {}
-            StreamedRequestInner::AsStream(items) => {
-                let mut write_access = items.lock().await;
-                match &mut *write_access {
-                    RequestAsStream::NotInitialized {
-                        has_end_of_stream, ..
-                    } => *has_end_of_stream = true,
-                    RequestAsStream::Initialized(sender) => {
-                        sender.take();
-                    }
-                }
+    /// Consumer of the previous attempt stops once the new one is started
+    pub fn start_consumer(&self) -> usize {
+        self.consumer_generation.fetch_add(1, Ordering::SeqCst) + 1
+    }
+
+    pub async fn get_next(&self, consumer_generation: usize, index: usize) -> Option<TItem> {
+        if self.consumer_generation.load(Ordering::SeqCst) != consumer_generation {
+            return None;
+        }
+
+        let inner = match &self.items {
+            StreamedRequestItems::AsVec(items) => return items.get(index).cloned(),
+            StreamedRequestItems::AsStream(inner) => inner,
+        };
+
+        if let Some(item) = inner.get_replayed(index) {
+            return Some(item);
+        }
+
+        let mut receiver = inner.receiver.lock().await;
+
+        if self.consumer_generation.load(Ordering::SeqCst) != consumer_generation {
+            return None;
+        }
+
+        // Item could be received by the consumer which held the lock before us
+        if let Some(item) = inner.get_replayed(index) {
+            return Some(item);
+        }
+
+        let Some(item) = receiver.recv().await else {
+            inner.eof_delivered.send_replace(true);
+            return None;
+        };
+
+        let mut replay_buffer = inner.replay_buffer.lock().unwrap();
+        replay_buffer.consumed += 1;
+
+        if let StreamedRequestRetryPolicy::Replay { max_items } = inner.retry_policy {
+            if replay_buffer.items.len() < max_items {
+                replay_buffer.items.push(item.clone());
+            } else {
+                replay_buffer.overflow = true;
             }
         }
+
+        Some(item)
     }
 }
@@ -1,24 +1,149 @@
 use std::{
     collections::{BTreeMap, HashMap, HashSet},
+    future::Future,
+    pin::Pin,
+    task::{Context, Poll},
     time::Duration,
 };
 
-use rust_extensions::chrono::format::Item;
+use futures_util::StreamExt;
+use tokio::time::Sleep;
 
-use crate::GrpcReadError;
+use crate::{
+    GrpcClientLimiterPermit, GrpcReadError, GrpcStreamTimeouts, StreamReadCounter,
+    StreamReadLimits, StreamReadTimer, StreamTimeout,
+};
 
 pub struct StreamedResponse<TItem> {
     stream: tonic::Streaming<TItem>,
-    time_out: Duration,
+    timer: StreamReadTimer,
+    read_limits: StreamReadLimits,
+    item_time_out: Option<(Pin<Box<Sleep>>, StreamTimeout)>,
+    finished: bool,
+    request_timeout: Duration,
+    permits: Vec<GrpcClientLimiterPermit>,
 }
 
 impl<TItem> StreamedResponse<TItem> {
-    pub fn new(stream: tonic::Streaming<TItem>, time_out: Duration) -> Self {
-        Self { stream, time_out }
+    pub fn new(stream: tonic::Streaming<TItem>, timeouts: impl Into<GrpcStreamTimeouts>) -> Self {
+        let timeouts = timeouts.into();
+        Self {
+            stream,
+            timer: StreamReadTimer::new(timeouts),
+            read_limits: StreamReadLimits::default(),
+            item_time_out: None,
+            finished: false,
+            request_timeout: timeouts
+                .idle_timeout
+                .or(timeouts.deadline)
+                .unwrap_or(Duration::MAX),
+            permits: Vec::new(),
+        }
+    }
+
+    /// Limiter permits are released once the stream is dropped
+    pub(crate) fn with_permits(mut self, permits: Vec<GrpcClientLimiterPermit>) -> Self {
+        self.permits = permits;
+        self
+    }
+
+    /// Timeout returned by `get_timeout` once the stream can be idle forever
+    pub(crate) fn with_request_timeout(mut self, request_timeout: Duration) -> Self {
+        self.request_timeout = request_timeout;
+        self
+    }
+
+    /// Continues reading from the new stream keeping the deadline of the current one
+    pub(crate) fn replace_stream(&mut self, other: StreamedResponse<TItem>) {
+        self.stream = other.stream;
+        self.permits = other.permits;
+        self.item_time_out = None;
+        self.finished = false;
+    }
+
+    /// Limits are applied while collecting items into collections
+    pub fn with_read_limits(mut self, read_limits: StreamReadLimits) -> Self {
+        self.read_limits = read_limits;
+        self
+    }
+
+    pub fn set_read_limits(&mut self, read_limits: StreamReadLimits) {
+        self.read_limits = read_limits;
+    }
+
+    pub fn get_read_limits(&self) -> StreamReadLimits {
+        self.read_limits
+    }
+
+    /// Applies all the read limits
+    pub async fn into_vec<TResult: From<TItem>>(self) -> Result<Vec<TResult>, GrpcReadError>
+    where
+        TItem: prost::Message,
+    {
+        self.into_vec_with_capacity_hint(0).await
+    }
+
+    pub async fn into_vec_with_capacity_hint<TResult: From<TItem>>(
+        mut self,
+        capacity: usize,
+    ) -> Result<Vec<TResult>, GrpcReadError>
+    where
+        TItem: prost::Message,
+    {
+        let capacity = match self.read_limits.max_items {
+            Some(max_items) => capacity.min(max_items),
+            None => capacity,
+        };
+
+        let mut result = Vec::with_capacity(capacity);
+        self.read_items(
+            usize::MAX,
+            |counter, item| counter.add(item),
+            |item| result.push(item.into()),
+        )
+        .await?;
+        Ok(result)
+    }
+
+    /// Reads up to `amount` items. Stream is cancelled once enough items are received
+    pub async fn take_items<TResult: From<TItem>>(
+        mut self,
+        amount: usize,
+    ) -> Result<Vec<TResult>, GrpcReadError>
+    where
+        TItem: prost::Message,
+    {
+        let mut result = Vec::with_capacity(amount);
+        self.read_items(
+            amount,
+            |counter, item| counter.add(item),
+            |item| result.push(item.into()),
+        )
+        .await?;
+        Ok(result)
     }
 
-    pub async fn into_vec<TResult: From<TItem>>(self) -> Result<Vec<TResult>, GrpcReadError> {
-        crate::read_grpc_stream::as_vec(self.stream, self.time_out).await
+    /// Stream is cancelled on drop once the limit is exceeded
+    async fn read_items(
+        &mut self,
+        max_amount: usize,
+        count: impl Fn(&mut StreamReadCounter, &TItem) -> Result<(), GrpcReadError>,
+        mut callback: impl FnMut(TItem),
+    ) -> Result<(), GrpcReadError> {
+        let mut counter = StreamReadCounter::new(self.read_limits);
+        let mut amount = 0;
+
+        while amount < max_amount {
+            let Some(item) = self.timer.read_next(&mut self.stream).await? else {
+                break;
+            };
+
+            count(&mut counter, &item)?;
+            callback(item);
+            amount += 1;
+        }
+
+        Ok(())
     }
 
     pub async fn get_single_item<TResult: From<TItem>>(
@@ -39,12 +164,37 @@ impl<TItem> StreamedResponse<TItem> {
         Ok(result)
     }
 
+    /// Sets idle timeout - max gap between two items
     pub fn set_timeout(&mut self, time_out: Duration) {
-        self.time_out = time_out
+        self.timer.set_idle_timeout(Some(time_out));
+    }
+
+    /// `None` - stream can be idle forever
+    pub fn set_idle_timeout(&mut self, idle_timeout: Option<Duration>) {
+        self.timer.set_idle_timeout(idle_timeout);
+    }
+
+    /// Deadline of the whole stream counted from now
+    pub fn set_deadline(&mut self, deadline: Option<Duration>) {
+        self.timer.set_deadline(deadline);
     }
 
+    /// Idle timeout. Request timeout is returned once the stream can be idle forever
     pub fn get_timeout(&mut self) -> Duration {
-        self.time_out
+        match self.timer.get_idle_timeout() {
+            Some(idle_timeout) => idle_timeout,
+            None => self.request_timeout,
+        }
+    }
+
+    /// `None` - stream can be idle forever
+    pub fn get_idle_timeout(&self) -> Option<Duration> {
+        self.timer.get_idle_timeout()
+    }
+
+    /// Idle timeout and the deadline left for the rest of the stream
+    pub fn get_timeouts(&self) -> GrpcStreamTimeouts {
+        self.timer.get_remaining_timeouts()
     }
 
     #[deprecated(note = "Please use into_vec and trait From to convert items")]
@@ -52,18 +202,31 @@ impl<TItem> StreamedResponse<TItem> {
         self,
         transform: impl Fn(TItem) -> TDest,
     ) -> Result<Vec<TDest>, GrpcReadError> {
-        crate::read_grpc_stream::as_vec_with_transformation(self.stream, self.time_out, &transform)
-            .await
+        let timeouts = self.timer.get_remaining_timeouts();
+        crate::read_grpc_stream::as_vec_with_transformation(self.stream, timeouts, &transform).await
     }
 
     pub async fn into_hash_map<TResult, TKey>(
-        self,
+        mut self,
         get_key: impl Fn(TItem) -> (TKey, TResult),
     ) -> Result<HashMap<TKey, TResult>, GrpcReadError>
     where
+        TItem: prost::Message,
         TKey: std::cmp::Eq + core::hash::Hash + Clone,
     {
-        crate::read_grpc_stream::as_hash_map(self.stream, get_key, self.time_out).await
+        let mut result = HashMap::new();
+
+        self.read_items(
+            usize::MAX,
+            |counter, item| counter.add(item),
+            |item| {
+                let (key, value) = get_key(item);
+                result.insert(key, value);
+            },
+        )
+        .await?;
+
+        Ok(result)
     }
 
     pub async fn into_hash_set<TKey>(
@@ -71,43 +234,182 @@ impl<TItem> StreamedResponse<TItem> {
         convert: impl Fn(TItem) -> TKey,
     ) -> Result<HashSet<TKey>, GrpcReadError>
     where
+        TItem: prost::Message,
         TKey: std::cmp::Eq + core::hash::Hash + Clone,
     {
         let mut result = HashSet::new();
 
-        while let Some(item) = self.get_next_item().await {
-            let item = item?;
-
-            result.insert(convert(item));
-        }
+        self.read_items(
+            usize::MAX,
+            |counter, item| counter.add(item),
+            |item| {
+                result.insert(convert(item));
+            },
+        )
+        .await?;
 
         Ok(result)
     }
 
     pub async fn into_b_tree_map<TResult, TKey>(
-        self,
+        mut self,
         get_key: impl Fn(TItem) -> (TKey, TResult),
     ) -> Result<BTreeMap<TKey, TResult>, GrpcReadError>
     where
+        TItem: prost::Message,
         TKey: Ord + core::hash::Hash + Clone,
     {
-        crate::read_grpc_stream::as_b_tree_map(self.stream, get_key, self.time_out).await
+        let mut result = BTreeMap::new();
+
+        self.read_items(
+            usize::MAX,
+            |counter, item| counter.add(item),
+            |item| {
+                let (key, value) = get_key(item);
+                result.insert(key, value);
+            },
+        )
+        .await?;
+
+        Ok(result)
+    }
+
+    /// Stream is over on the first error
+    pub async fn fold<TAcc, TFuture: Future<Output = TAcc>>(
+        mut self,
+        init: TAcc,
+        mut fold: impl FnMut(TAcc, TItem) -> TFuture,
+    ) -> Result<TAcc, GrpcReadError> {
+        let mut result = init;
+
+        while let Some(item) = self.next().await {
+            result = fold(result, item?).await;
+        }
+
+        Ok(result)
+    }
+
+    pub async fn for_each<TFuture: Future<Output = ()>>(
+        mut self,
+        mut callback: impl FnMut(TItem) -> TFuture,
+    ) -> Result<(), GrpcReadError> {
+        while let Some(item) = self.next().await {
+            callback(item?).await;
+        }
+
+        Ok(())
+    }
+
+    pub fn try_into_stream<TResult: From<TItem>>(
+        self,
+    ) -> impl futures_util::Stream<Item = Result<TResult, GrpcReadError>> {
+        self.map(|item| item.map(TResult::from))
     }
 
     pub async fn get_next_item(&mut self) -> Option<tonic::Result<TItem>> {
-        use futures_util::StreamExt;
-        let future = self.stream.next();
-
-        let result = match tokio::time::timeout(self.time_out, future).await {
-            Ok(result) => result,
-            Err(_) => {
-                return Some(Err(tonic::Status::aborted(format!(
-                    "Timeout {:?}",
-                    self.time_out
-                ))))
+        match self.timer.read_next(&mut self.stream).await {
+            Ok(item) => item.map(Ok),
+            Err(GrpcReadError::DeadlineExceeded) => Some(Err(tonic::Status::deadline_exceeded(
+                "Stream deadline is exceeded",
+            ))),
+            Err(GrpcReadError::TonicStatus(status)) => Some(Err(status)),
+            Err(_) => Some(Err(tonic::Status::aborted(format!(
+                "Timeout {:?}",
+                self.timer.get_idle_timeout()
+            )))),
+        }
+    }
+}
+
+/// Each item has to be received within the idle timeout and before the deadline. Stream is over after the first error
+impl<TItem> futures_util::Stream for StreamedResponse<TItem> {
+    type Item = Result<TItem, GrpcReadError>;
+
+    fn poll_next(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<Self::Item>> {
+        let this = self.get_mut();
+
+        if this.finished {
+            return Poll::Ready(None);
+        }
+
+        if let Poll::Ready(item) = Pin::new(&mut this.stream).poll_next(cx) {
+            this.item_time_out = None;
+
+            return match item {
+                Some(Ok(item)) => Poll::Ready(Some(Ok(item))),
+                Some(Err(status)) => {
+                    this.finished = true;
+                    Poll::Ready(Some(Err(status.into())))
+                }
+                None => {
+                    this.finished = true;
+                    Poll::Ready(None)
+                }
+            };
+        }
+
+        if this.item_time_out.is_none() {
+            if let Some((wake_up, timeout)) = this.timer.get_next_item_wake_up() {
+                this.item_time_out = Some((Box::pin(tokio::time::sleep_until(wake_up)), timeout));
             }
-        }?;
+        }
+
+        if let Some((sleep, timeout)) = this.item_time_out.as_mut() {
+            if sleep.as_mut().poll(cx).is_ready() {
+                let err = timeout.into_error();
+                this.item_time_out = None;
+                this.finished = true;
+                return Poll::Ready(Some(Err(err)));
+            }
+        }
+
+        Poll::Pending
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::Duration;
+
+    use tonic::codec::{DecodeBuf, Decoder};
+
+    use super::*;
+    use crate::{GrpcClientLimiter, GrpcClientLimits};
+
+    struct EmptyDecoder;
+
+    impl Decoder for EmptyDecoder {
+        type Item = ();
+        type Error = tonic::Status;
+
+        fn decode(&mut self, _: &mut DecodeBuf<'_>) -> Result<Option<()>, tonic::Status> {
+            Ok(None)
+        }
+    }
+
+    #[test]
+    fn test_permits_are_kept_while_stream_is_open() {
+        let rt = tokio::runtime::Builder::new_current_thread()
+            .enable_time()
+            .build()
+            .unwrap();
+
+        rt.block_on(async {
+            let limiter = GrpcClientLimiter::new(GrpcClientLimits::new().with_max_in_flight(1));
+
+            let stream = tonic::Streaming::new_empty(EmptyDecoder, tonic::body::Body::empty());
+            let permit = limiter.acquire().await.unwrap();
+
+            let response =
+                StreamedResponse::new(stream, Duration::from_secs(1)).with_permits(vec![permit]);
+
+            assert!(matches!(
+                limiter.acquire().await,
+                Err(GrpcReadError::ClientLimitExceeded(_))
+            ));
 
-        Some(result)
+            drop(response);
+            assert!(limiter.acquire().await.is_ok());
+        });
     }
 }
@@ -0,0 +1,311 @@
+use std::{
+    sync::{Arc, Mutex, RwLock},
+    time::Duration,
+};
+
+use tokio::{
+    sync::{OwnedSemaphorePermit, Semaphore},
+    time::Instant,
+};
+
+use crate::{token_bucket::TokenBucket, GrpcReadError};
+
+/// Limits of requests sent by the client. No limits by default
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct GrpcClientLimits {
+    /// Token bucket refilled with this rate. Bucket size is one second of requests
+    pub max_requests_per_second: Option<u32>,
+    pub max_in_flight: Option<usize>,
+    /// Max time request waits for the limits. `None` - request fails immediately once limit is reached
+    pub queue_timeout: Option<Duration>,
+}
+
+impl GrpcClientLimits {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn with_max_requests_per_second(mut self, max_requests_per_second: u32) -> Self {
+        self.max_requests_per_second = Some(max_requests_per_second);
+        self
+    }
+
+    pub fn with_max_in_flight(mut self, max_in_flight: usize) -> Self {
+        self.max_in_flight = Some(max_in_flight);
+        self
+    }
+
+    pub fn with_queue_timeout(mut self, queue_timeout: Duration) -> Self {
+        self.queue_timeout = Some(queue_timeout);
+        self
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum GrpcClientLimit {
+    MaxRequestsPerSecond(u32),
+    MaxInFlight(usize),
+}
+
+struct LimiterState {
+    limits: GrpcClientLimits,
+    bucket: Option<Mutex<TokenBucket>>,
+    in_flight: Option<Arc<Semaphore>>,
+}
+
+impl LimiterState {
+    fn new(limits: GrpcClientLimits) -> Self {
+        Self {
+            bucket: limits
+                .max_requests_per_second
+                .map(|max_requests_per_second| {
+                    Mutex::new(TokenBucket::new(max_requests_per_second))
+                }),
+            in_flight: limits
+                .max_in_flight
+                .map(|max_in_flight| Arc::new(Semaphore::new(max_in_flight))),
+            limits,
+        }
+    }
+
+    /// Returns the time to wait until the token is refilled
+    fn take_token(&self) -> Result<Duration, GrpcReadError> {
+        let Some(bucket) = self.bucket.as_ref() else {
+            return Ok(Duration::ZERO);
+        };
+
+        let mut bucket = bucket.lock().unwrap();
+
+        let wait = bucket.take();
+
+        if wait.is_zero() {
+            return Ok(wait);
+        }
+
+        match self.limits.queue_timeout {
+            Some(queue_timeout) if wait <= queue_timeout => Ok(wait),
+            _ => {
+                bucket.give_back();
+                Err(GrpcReadError::ClientLimitExceeded(
+                    GrpcClientLimit::MaxRequestsPerSecond(
+                        self.limits.max_requests_per_second.unwrap_or_default(),
+                    ),
+                ))
+            }
+        }
+    }
+
+    fn give_back_token(&self) {
+        if let Some(bucket) = self.bucket.as_ref() {
+            bucket.lock().unwrap().give_back();
+        }
+    }
+}
+
+/// Gives the rate token back once the request is not sent: in-flight limit is exceeded or waiting is cancelled
+struct TakenToken<'s> {
+    state: Option<&'s LimiterState>,
+}
+
+impl TakenToken<'_> {
+    fn keep(mut self) {
+        self.state = None;
+    }
+}
+
+impl Drop for TakenToken<'_> {
+    fn drop(&mut self) {
+        if let Some(state) = self.state {
+            state.give_back_token();
+        }
+    }
+}
+
+/// Keeps the in-flight slot until dropped
+pub struct GrpcClientLimiterPermit {
+    _in_flight: Option<OwnedSemaphorePermit>,
+}
+
+/// Token bucket rate limit and max in-flight requests. Limits can be changed at runtime,
+/// requests which are already in flight are not counted by the new limits
+pub struct GrpcClientLimiter {
+    state: RwLock<Arc<LimiterState>>,
+}
+
+impl GrpcClientLimiter {
+    pub fn new(limits: GrpcClientLimits) -> Self {
+        Self {
+            state: RwLock::new(Arc::new(LimiterState::new(limits))),
+        }
+    }
+
+    pub fn set_limits(&self, limits: GrpcClientLimits) {
+        *self.state.write().unwrap() = Arc::new(LimiterState::new(limits));
+    }
+
+    pub fn get_limits(&self) -> GrpcClientLimits {
+        self.state.read().unwrap().limits
+    }
+
+    pub async fn acquire(&self) -> Result<GrpcClientLimiterPermit, GrpcReadError> {
+        let state = self.state.read().unwrap().clone();
+        let started = Instant::now();
+
+        let wait = state.take_token()?;
+        let token = TakenToken {
+            state: Some(&state),
+        };
+
+        if !wait.is_zero() {
+            tokio::time::sleep(wait).await;
+        }
+
+        let Some(in_flight) = state.in_flight.clone() else {
+            token.keep();
+            return Ok(GrpcClientLimiterPermit { _in_flight: None });
+        };
+
+        let max_in_flight_exceeded = || {
+            GrpcReadError::ClientLimitExceeded(GrpcClientLimit::MaxInFlight(
+                state.limits.max_in_flight.unwrap_or_default(),
+            ))
+        };
+
+        let permit = match state.limits.queue_timeout {
+            Some(queue_timeout) => {
+                let result =
+                    tokio::time::timeout_at(started + queue_timeout, in_flight.acquire_owned())
+                        .await;
+
+                match result {
+                    Ok(Ok(permit)) => permit,
+                    _ => return Err(max_in_flight_exceeded()),
+                }
+            }
+            None => match in_flight.try_acquire_owned() {
+                Ok(permit) => permit,
+                Err(_) => return Err(max_in_flight_exceeded()),
+            },
+        };
+
+        token.keep();
+
+        Ok(GrpcClientLimiterPermit {
+            _in_flight: Some(permit),
+        })
+    }
+}
+
+impl Default for GrpcClientLimiter {
+    fn default() -> Self {
+        Self::new(GrpcClientLimits::default())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::Duration;
+
+    use super::*;
+
+    fn run(future: impl std::future::Future<Output = ()>) {
+        tokio::runtime::Builder::new_current_thread()
+            .enable_time()
+            .build()
+            .unwrap()
+            .block_on(future);
+    }
+
+    #[test]
+    fn test_max_in_flight() {
+        run(async {
+            let limiter = GrpcClientLimiter::new(GrpcClientLimits::new().with_max_in_flight(1));
+
+            let permit = limiter.acquire().await.unwrap();
+
+            let err = limiter.acquire().await.err().unwrap();
+            assert!(matches!(
+                err,
+                GrpcReadError::ClientLimitExceeded(GrpcClientLimit::MaxInFlight(1))
+            ));
+
+            drop(permit);
+            assert!(limiter.acquire().await.is_ok());
+
+            limiter.set_limits(
+                GrpcClientLimits::new()
+                    .with_max_in_flight(1)
+                    .with_queue_timeout(Duration::from_millis(10)),
+            );
+
+            let _permit = limiter.acquire().await.unwrap();
+            let started = Instant::now();
+            assert!(limiter.acquire().await.is_err());
+            assert!(started.elapsed() >= Duration::from_millis(10));
+        });
+    }
+
+    #[test]
+    fn test_requests_per_second() {
+        run(async {
+            let limiter =
+                GrpcClientLimiter::new(GrpcClientLimits::new().with_max_requests_per_second(10));
+
+            for _ in 0..10 {
+                assert!(limiter.acquire().await.is_ok());
+            }
+
+            let err = limiter.acquire().await.err().unwrap();
+            assert!(matches!(
+                err,
+                GrpcReadError::ClientLimitExceeded(GrpcClientLimit::MaxRequestsPerSecond(10))
+            ));
+
+            limiter.set_limits(
+                GrpcClientLimits::new()
+                    .with_max_requests_per_second(10)
+                    .with_queue_timeout(Duration::from_secs(1)),
+            );
+
+            for _ in 0..10 {
+                assert!(limiter.acquire().await.is_ok());
+            }
+
+            let started = Instant::now();
+            assert!(limiter.acquire().await.is_ok());
+            assert!(started.elapsed() >= Duration::from_millis(50));
+        });
+    }
+
+    #[test]
+    fn test_token_is_given_back_once_request_is_not_sent() {
+        run(async {
+            let limiter = GrpcClientLimiter::new(
+                GrpcClientLimits::new()
+                    .with_max_requests_per_second(2)
+                    .with_max_in_flight(1),
+            );
+
+            let permit = limiter.acquire().await.unwrap();
+            assert!(limiter.acquire().await.is_err());
+
+            drop(permit);
+            assert!(limiter.acquire().await.is_ok());
+
+            limiter.set_limits(
+                GrpcClientLimits::new()
+                    .with_max_requests_per_second(2)
+                    .with_queue_timeout(Duration::from_millis(700)),
+            );
+
+            assert!(limiter.acquire().await.is_ok());
+            assert!(limiter.acquire().await.is_ok());
+
+            let cancelled =
+                tokio::time::timeout(Duration::from_millis(10), limiter.acquire()).await;
+            assert!(cancelled.is_err());
+
+            assert!(limiter.acquire().await.is_ok());
+        });
+    }
+}
@@ -0,0 +1,4 @@
+pub use tonic_types::{
+    BadRequest, ErrorDetails, ErrorInfo, FieldViolation, QuotaFailure, QuotaViolation, RetryInfo,
+    StatusExt,
+};
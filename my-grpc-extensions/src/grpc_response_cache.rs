@@ -0,0 +1,417 @@
+use std::{
+    collections::HashMap,
+    future::Future,
+    sync::{
+        atomic::{AtomicBool, Ordering},
+        Arc,
+    },
+    time::Duration,
+};
+
+use tokio::{
+    sync::{Mutex, OnceCell},
+    time::Instant,
+};
+
+use crate::GrpcReadError;
+
+pub const DEFAULT_RESPONSE_CACHE_MAX_ENTRIES: usize = 1000;
+
+#[derive(Debug, Clone, Copy)]
+pub struct GrpcResponseCacheOptions {
+    pub ttl: Duration,
+    pub max_entries: usize,
+    /// Response older than ttl is still returned within this window while one of the callers refreshes it
+    pub stale_while_revalidate: Duration,
+}
+
+impl GrpcResponseCacheOptions {
+    pub fn new(ttl: Duration) -> Self {
+        Self {
+            ttl,
+            max_entries: DEFAULT_RESPONSE_CACHE_MAX_ENTRIES,
+            stale_while_revalidate: Duration::ZERO,
+        }
+    }
+
+    pub fn with_max_entries(mut self, max_entries: usize) -> Self {
+        self.max_entries = max_entries;
+        self
+    }
+
+    pub fn with_stale_while_revalidate(mut self, stale_while_revalidate: Duration) -> Self {
+        self.stale_while_revalidate = stale_while_revalidate;
+        self
+    }
+}
+
+struct CachedResponse<T> {
+    value: T,
+    loaded: Instant,
+}
+
+struct GrpcResponseCacheItem<T> {
+    value: Arc<OnceCell<CachedResponse<T>>>,
+    revalidating: Arc<AtomicBool>,
+}
+
+/// Lets next caller refresh the item once the refresh is failed or cancelled
+struct RevalidationGuard(Arc<AtomicBool>);
+
+impl Drop for RevalidationGuard {
+    fn drop(&mut self) {
+        self.0.store(false, Ordering::SeqCst);
+    }
+}
+
+enum CacheLookup<T> {
+    Fresh(T),
+    Stale(T, RevalidationGuard),
+    Load(Arc<OnceCell<CachedResponse<T>>>),
+}
+
+/// Requests are equal if their protobuf encoded bytes are equal
+pub fn get_response_cache_key(request: &impl prost::Message) -> Vec<u8> {
+    request.encode_to_vec()
+}
+
+/// Cache of unary responses keyed by the protobuf encoded request.
+/// Concurrent identical requests are coalesced into a single call. Errors are not cached.
+pub struct GrpcResponseCache<T: Clone + Send + Sync + 'static> {
+    options: GrpcResponseCacheOptions,
+    items: Mutex<HashMap<Vec<u8>, GrpcResponseCacheItem<T>>>,
+}
+
+impl<T: Clone + Send + Sync + 'static> GrpcResponseCache<T> {
+    pub fn new(options: GrpcResponseCacheOptions) -> Self {
+        Self {
+            options,
+            items: Mutex::new(HashMap::new()),
+        }
+    }
+
+    pub async fn get_or_fetch(
+        &self,
+        cache_key: Vec<u8>,
+        fetch: impl Future<Output = Result<T, GrpcReadError>>,
+    ) -> Result<T, GrpcReadError> {
+        let fetch = async move {
+            let value = fetch.await?;
+            Ok::<_, GrpcReadError>(CachedResponse {
+                value,
+                loaded: Instant::now(),
+            })
+        };
+
+        match self.lookup(&cache_key).await {
+            CacheLookup::Fresh(value) => Ok(value),
+            CacheLookup::Load(cell) => match cell.get_or_try_init(|| fetch).await {
+                Ok(result) => Ok(result.value.clone()),
+                Err(err) => {
+                    self.remove_uninitialized(&cache_key, &cell).await;
+                    Err(err)
+                }
+            },
+            CacheLookup::Stale(stale_value, _revalidation) => {
+                let Ok(response) = fetch.await else {
+                    return Ok(stale_value);
+                };
+
+                let value = response.value.clone();
+                self.items.lock().await.insert(
+                    cache_key,
+                    GrpcResponseCacheItem {
+                        value: Arc::new(OnceCell::new_with(Some(response))),
+                        revalidating: Arc::new(AtomicBool::new(false)),
+                    },
+                );
+
+                Ok(value)
+            }
+        }
+    }
+
+    pub async fn remove(&self, cache_key: &[u8]) {
+        self.items.lock().await.remove(cache_key);
+    }
+
+    pub async fn clear(&self) {
+        self.items.lock().await.clear();
+    }
+
+    /// Item is kept if it was already replaced or loaded by another caller
+    async fn remove_uninitialized(
+        &self,
+        cache_key: &[u8],
+        cell: &Arc<OnceCell<CachedResponse<T>>>,
+    ) {
+        let mut items = self.items.lock().await;
+
+        let Some(item) = items.get(cache_key) else {
+            return;
+        };
+
+        if Arc::ptr_eq(&item.value, cell) && !item.value.initialized() {
+            items.remove(cache_key);
+        }
+    }
+
+    async fn lookup(&self, cache_key: &Vec<u8>) -> CacheLookup<T> {
+        let now = Instant::now();
+        let mut items = self.items.lock().await;
+
+        if let Some(item) = items.get_mut(cache_key) {
+            let Some(cached) = item.value.get() else {
+                return CacheLookup::Load(item.value.clone());
+            };
+
+            let age = now.duration_since(cached.loaded);
+
+            if age < self.options.ttl {
+                return CacheLookup::Fresh(cached.value.clone());
+            }
+
+            if age < self.options.ttl + self.options.stale_while_revalidate {
+                if item.revalidating.swap(true, Ordering::SeqCst) {
+                    return CacheLookup::Fresh(cached.value.clone());
+                }
+
+                return CacheLookup::Stale(
+                    cached.value.clone(),
+                    RevalidationGuard(item.revalidating.clone()),
+                );
+            }
+        }
+
+        if items.len() >= self.options.max_entries && !items.contains_key(cache_key) {
+            self.evict(&mut items, now);
+        }
+
+        let value = Arc::new(OnceCell::new());
+        items.insert(
+            cache_key.clone(),
+            GrpcResponseCacheItem {
+                value: value.clone(),
+                revalidating: Arc::new(AtomicBool::new(false)),
+            },
+        );
+
+        CacheLookup::Load(value)
+    }
+
+    /// Removes expired items and items of cancelled fetches. If there are none - the oldest one
+    fn evict(&self, items: &mut HashMap<Vec<u8>, GrpcResponseCacheItem<T>>, now: Instant) {
+        let max_age = self.options.ttl + self.options.stale_while_revalidate;
+
+        items.retain(|_, item| match item.value.get() {
+            Some(cached) => now.duration_since(cached.loaded) < max_age,
+            // Nobody is loading the item once only the cache holds it
+            None => Arc::strong_count(&item.value) > 1,
+        });
+
+        if items.len() < self.options.max_entries {
+            return;
+        }
+
+        let oldest = items
+            .iter()
+            .filter_map(|(key, item)| item.value.get().map(|cached| (key, cached.loaded)))
+            .min_by_key(|(_, loaded)| *loaded)
+            .map(|(key, _)| key.clone())
+            .or_else(|| items.keys().next().cloned());
+
+        if let Some(oldest) = oldest {
+            items.remove(&oldest);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::atomic::{AtomicUsize, Ordering};
+
+    use super::*;
+
+    fn run(future: impl Future<Output = ()>) {
+        tokio::runtime::Builder::new_current_thread()
+            .enable_time()
+            .build()
+            .unwrap()
+            .block_on(future);
+    }
+
+    #[test]
+    fn test_ttl_and_single_flight() {
+        run(async {
+            let cache = GrpcResponseCache::new(GrpcResponseCacheOptions::new(
+                Duration::from_millis(100),
+            ));
+            let calls = AtomicUsize::new(0);
+
+            let fetch = || async {
+                calls.fetch_add(1, Ordering::SeqCst);
+                tokio::time::sleep(Duration::from_millis(10)).await;
+                Ok(calls.load(Ordering::SeqCst))
+            };
+
+            let key = get_response_cache_key(&"key".to_string());
+
+            let (first, second) = futures::join!(
+                cache.get_or_fetch(key.clone(), fetch()),
+                cache.get_or_fetch(key.clone(), fetch())
+            );
+            assert_eq!(first.unwrap(), 1);
+            assert_eq!(second.unwrap(), 1);
+
+            assert_eq!(cache.get_or_fetch(key.clone(), fetch()).await.unwrap(), 1);
+
+            tokio::time::sleep(Duration::from_millis(150)).await;
+            assert_eq!(cache.get_or_fetch(key.clone(), fetch()).await.unwrap(), 2);
+        });
+    }
+
+    #[test]
+    fn test_errors_are_not_cached() {
+        run(async {
+            let cache = GrpcResponseCache::new(GrpcResponseCacheOptions::new(
+                Duration::from_secs(1),
+            ));
+            let key = vec![1];
+
+            let result = cache
+                .get_or_fetch(key.clone(), async { Err(GrpcReadError::Timeout) })
+                .await;
+            assert!(result.is_err());
+
+            let result = cache.get_or_fetch(key.clone(), async { Ok(5) }).await;
+            assert_eq!(result.unwrap(), 5);
+        });
+    }
+
+    #[test]
+    fn test_failed_and_cancelled_fetches_are_removed() {
+        run(async {
+            let cache = GrpcResponseCache::new(
+                GrpcResponseCacheOptions::new(Duration::from_secs(10)).with_max_entries(2),
+            );
+
+            let result = cache
+                .get_or_fetch(vec![1], async { Err(GrpcReadError::Timeout) })
+                .await;
+            assert!(result.is_err());
+            assert!(cache.items.lock().await.is_empty());
+
+            let result = tokio::time::timeout(
+                Duration::from_millis(10),
+                cache.get_or_fetch(vec![2], std::future::pending()),
+            )
+            .await;
+            assert!(result.is_err());
+            assert_eq!(cache.items.lock().await.len(), 1);
+
+            cache.get_or_fetch(vec![3], async { Ok(3) }).await.unwrap();
+            cache.get_or_fetch(vec![4], async { Ok(4) }).await.unwrap();
+
+            let items = cache.items.lock().await;
+            assert_eq!(items.len(), 2);
+            assert!(!items.contains_key(&vec![2]));
+        });
+    }
+
+    #[test]
+    fn test_stale_while_revalidate() {
+        run(async {
+            let cache = GrpcResponseCache::new(
+                GrpcResponseCacheOptions::new(Duration::from_millis(50))
+                    .with_stale_while_revalidate(Duration::from_secs(10)),
+            );
+            let key = vec![1];
+
+            assert_eq!(
+                cache
+                    .get_or_fetch(key.clone(), async { Ok(1) })
+                    .await
+                    .unwrap(),
+                1
+            );
+
+            tokio::time::sleep(Duration::from_millis(100)).await;
+
+            let result = cache
+                .get_or_fetch(key.clone(), async { Err(GrpcReadError::Timeout) })
+                .await;
+            assert_eq!(result.unwrap(), 1);
+
+            assert_eq!(
+                cache
+                    .get_or_fetch(key.clone(), async { Ok(2) })
+                    .await
+                    .unwrap(),
+                2
+            );
+            assert_eq!(
+                cache
+                    .get_or_fetch(key.clone(), async { Ok(3) })
+                    .await
+                    .unwrap(),
+                2
+            );
+        });
+    }
+
+    #[test]
+    fn test_cancelled_revalidation_is_restarted() {
+        run(async {
+            let cache = GrpcResponseCache::new(
+                GrpcResponseCacheOptions::new(Duration::from_millis(50))
+                    .with_stale_while_revalidate(Duration::from_secs(10)),
+            );
+            let key = vec![1];
+
+            assert_eq!(
+                cache
+                    .get_or_fetch(key.clone(), async { Ok(1) })
+                    .await
+                    .unwrap(),
+                1
+            );
+
+            tokio::time::sleep(Duration::from_millis(100)).await;
+
+            let result = tokio::time::timeout(
+                Duration::from_millis(10),
+                cache.get_or_fetch(key.clone(), std::future::pending()),
+            )
+            .await;
+            assert!(result.is_err());
+
+            assert_eq!(
+                cache
+                    .get_or_fetch(key.clone(), async { Ok(2) })
+                    .await
+                    .unwrap(),
+                2
+            );
+        });
+    }
+
+    #[test]
+    fn test_max_entries() {
+        run(async {
+            let cache = GrpcResponseCache::new(
+                GrpcResponseCacheOptions::new(Duration::from_secs(10)).with_max_entries(2),
+            );
+
+            cache.get_or_fetch(vec![1], async { Ok(1) }).await.unwrap();
+            tokio::time::sleep(Duration::from_millis(1)).await;
+            cache.get_or_fetch(vec![2], async { Ok(2) }).await.unwrap();
+            cache.get_or_fetch(vec![3], async { Ok(3) }).await.unwrap();
+
+            assert_eq!(cache.items.lock().await.len(), 2);
+            assert_eq!(
+                cache.get_or_fetch(vec![1], async { Ok(10) }).await.unwrap(),
+                10
+            );
+        });
+    }
+}
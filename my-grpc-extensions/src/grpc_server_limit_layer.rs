@@ -0,0 +1,221 @@
+use std::{
+    collections::HashMap,
+    future::Future,
+    pin::Pin,
+    sync::Arc,
+    task::{Context, Poll},
+};
+
+use tonic::{codegen::http, transport::server::TcpConnectInfo};
+
+use crate::{GrpcServerLimiter, GrpcServerLimiterPermit};
+
+#[derive(Default)]
+struct ServerLimiters {
+    service: Option<GrpcServerLimiter>,
+    methods: HashMap<String, GrpcServerLimiter>,
+}
+
+impl ServerLimiters {
+    fn try_acquire(
+        &self,
+        path: &str,
+        remote_addr: Option<std::net::SocketAddr>,
+    ) -> Result<Vec<GrpcServerLimiterPermit>, tonic::Status> {
+        let mut result = Vec::with_capacity(2);
+
+        if let Some(service) = self.service.as_ref() {
+            result.push(service.try_acquire(remote_addr)?);
+        }
+
+        if let Some(limiter) = self.methods.get(path) {
+            match limiter.try_acquire(remote_addr) {
+                Ok(permit) => result.push(permit),
+                Err(status) => {
+                    if let Some(service) = self.service.as_ref() {
+                        service.give_back(remote_addr);
+                    }
+                    return Err(status);
+                }
+            }
+        }
+
+        Ok(result)
+    }
+}
+
+/// Tower layer applying `GrpcServerLimiter` to the requests of the tonic server:
+///
+/// `Server::builder().layer(GrpcServerLimitLayer::new().with_method_limiter("/keyvalue.KeyValueGrpcService/Get", limiter))`
+#[derive(Clone, Default)]
+pub struct GrpcServerLimitLayer {
+    limiters: Arc<ServerLimiters>,
+}
+
+impl GrpcServerLimitLayer {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    fn get_limiters_mut(&mut self) -> &mut ServerLimiters {
+        match Arc::get_mut(&mut self.limiters) {
+            Some(limiters) => limiters,
+            None => panic!("GrpcServerLimitLayer can not be changed once it is cloned"),
+        }
+    }
+
+    /// Limiter applied to all the requests
+    pub fn with_limiter(mut self, limiter: GrpcServerLimiter) -> Self {
+        self.get_limiters_mut().service = Some(limiter);
+        self
+    }
+
+    /// `path` is the grpc path of the rpc: `/package.Service/Method`
+    pub fn with_method_limiter(mut self, path: &str, limiter: GrpcServerLimiter) -> Self {
+        self.get_limiters_mut()
+            .methods
+            .insert(path.to_string(), limiter);
+        self
+    }
+}
+
+impl<S> tower::Layer<S> for GrpcServerLimitLayer {
+    type Service = GrpcServerLimitService<S>;
+
+    fn layer(&self, inner: S) -> Self::Service {
+        GrpcServerLimitService {
+            inner,
+            limiters: self.limiters.clone(),
+        }
+    }
+}
+
+#[derive(Clone)]
+pub struct GrpcServerLimitService<S> {
+    inner: S,
+    limiters: Arc<ServerLimiters>,
+}
+
+/// Response body keeping the limiter permits, so streaming rpcs are counted until the response is sent
+pub struct GrpcServerLimitBody<TBody> {
+    body: Pin<Box<TBody>>,
+    _permits: Vec<GrpcServerLimiterPermit>,
+}
+
+impl<TBody: Default> Default for GrpcServerLimitBody<TBody> {
+    fn default() -> Self {
+        Self {
+            body: Box::pin(TBody::default()),
+            _permits: Vec::new(),
+        }
+    }
+}
+
+impl<TBody: http_body::Body> http_body::Body for GrpcServerLimitBody<TBody> {
+    type Data = TBody::Data;
+    type Error = TBody::Error;
+
+    fn poll_frame(
+        self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+    ) -> Poll<Option<Result<http_body::Frame<Self::Data>, Self::Error>>> {
+        self.get_mut().body.as_mut().poll_frame(cx)
+    }
+
+    fn is_end_stream(&self) -> bool {
+        self.body.is_end_stream()
+    }
+
+    fn size_hint(&self) -> http_body::SizeHint {
+        self.body.size_hint()
+    }
+}
+
+impl<S, TReqBody, TResBody> tower::Service<http::Request<TReqBody>> for GrpcServerLimitService<S>
+where
+    S: tower::Service<http::Request<TReqBody>, Response = http::Response<TResBody>>,
+    S::Future: Send + 'static,
+    S::Error: Send + 'static,
+    TResBody: Default + Send + 'static,
+{
+    type Response = http::Response<GrpcServerLimitBody<TResBody>>;
+    type Error = S::Error;
+    type Future = Pin<Box<dyn Future<Output = Result<Self::Response, S::Error>> + Send>>;
+
+    fn poll_ready(&mut self, cx: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+        self.inner.poll_ready(cx)
+    }
+
+    fn call(&mut self, request: http::Request<TReqBody>) -> Self::Future {
+        let remote_addr = request
+            .extensions()
+            .get::<TcpConnectInfo>()
+            .and_then(|info| info.remote_addr());
+
+        match self.limiters.try_acquire(request.uri().path(), remote_addr) {
+            Ok(permits) => {
+                let future = self.inner.call(request);
+                Box::pin(async move {
+                    let response = future.await?;
+                    Ok(response.map(|body| GrpcServerLimitBody {
+                        body: Box::pin(body),
+                        _permits: permits,
+                    }))
+                })
+            }
+            Err(status) => {
+                let response = status.into_http();
+                Box::pin(async move { Ok(response) })
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::GrpcServerLimits;
+
+    use super::*;
+
+    #[test]
+    fn test_permits_are_kept_until_response_body_is_dropped() {
+        let rt = tokio::runtime::Builder::new_current_thread()
+            .enable_time()
+            .build()
+            .unwrap();
+
+        rt.block_on(async {
+            let layer = GrpcServerLimitLayer::new().with_limiter(
+                GrpcServerLimiter::new("KeyValue")
+                    .with_limits(GrpcServerLimits::new().with_max_concurrent(1)),
+            );
+
+            let mut service = tower::Layer::layer(
+                &layer,
+                tower::service_fn(|_: http::Request<()>| async {
+                    Ok::<_, std::convert::Infallible>(http::Response::new(
+                        tonic::body::Body::empty(),
+                    ))
+                }),
+            );
+
+            let request = || {
+                http::Request::builder()
+                    .uri("/keyvalue.KeyValueGrpcService/Subscribe")
+                    .body(())
+                    .unwrap()
+            };
+
+            let stream = tower::Service::call(&mut service, request()).await.unwrap();
+            assert!(stream.headers().get("grpc-status").is_none());
+
+            let rejected = tower::Service::call(&mut service, request()).await.unwrap();
+            assert_eq!(rejected.headers().get("grpc-status").unwrap(), "8");
+
+            drop(stream);
+
+            let response = tower::Service::call(&mut service, request()).await.unwrap();
+            assert!(response.headers().get("grpc-status").is_none());
+        });
+    }
+}
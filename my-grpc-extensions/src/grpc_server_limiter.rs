@@ -0,0 +1,411 @@
+use std::{
+    collections::HashMap,
+    net::{IpAddr, SocketAddr},
+    pin::Pin,
+    sync::{
+        atomic::{AtomicUsize, Ordering},
+        Arc, Mutex,
+    },
+    task::{Context, Poll},
+    time::Duration,
+};
+
+use tokio::time::Instant;
+use tonic_types::{ErrorDetails, StatusExt};
+
+use crate::token_bucket::TokenBucket;
+
+pub const DEFAULT_SERVER_LIMIT_RETRY_DELAY: Duration = Duration::from_millis(100);
+
+const PER_IP_CLEANUP_INTERVAL: Duration = Duration::from_secs(60);
+
+/// Admission limits of the server. No limits by default
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct GrpcServerLimits {
+    pub max_concurrent: Option<usize>,
+    /// Token bucket refilled with this rate. Bucket size is one second of requests
+    pub max_requests_per_second: Option<u32>,
+}
+
+impl GrpcServerLimits {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn with_max_concurrent(mut self, max_concurrent: usize) -> Self {
+        self.max_concurrent = Some(max_concurrent);
+        self
+    }
+
+    pub fn with_max_requests_per_second(mut self, max_requests_per_second: u32) -> Self {
+        self.max_requests_per_second = Some(max_requests_per_second);
+        self
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.max_concurrent.is_none() && self.max_requests_per_second.is_none()
+    }
+}
+
+struct LimitCounter {
+    in_flight: Arc<AtomicUsize>,
+    bucket: Option<TokenBucket>,
+    last_used: Instant,
+}
+
+impl LimitCounter {
+    fn new(limits: &GrpcServerLimits) -> Self {
+        Self {
+            in_flight: Arc::new(AtomicUsize::new(0)),
+            bucket: limits.max_requests_per_second.map(TokenBucket::new),
+            last_used: Instant::now(),
+        }
+    }
+
+    /// Returns the description of exceeded limit with the retry delay
+    fn try_acquire(
+        &mut self,
+        limits: &GrpcServerLimits,
+        retry_delay: Duration,
+    ) -> Result<InFlightGuard, (String, Duration)> {
+        self.last_used = Instant::now();
+
+        if let Some(max_concurrent) = limits.max_concurrent {
+            if self.in_flight.load(Ordering::SeqCst) >= max_concurrent {
+                return Err((
+                    format!("max_concurrent {} is reached", max_concurrent),
+                    retry_delay,
+                ));
+            }
+        }
+
+        if let Some(bucket) = self.bucket.as_mut() {
+            let wait = bucket.take();
+
+            if !wait.is_zero() {
+                bucket.give_back();
+                return Err((
+                    format!(
+                        "max_requests_per_second {} is reached",
+                        limits.max_requests_per_second.unwrap_or_default()
+                    ),
+                    wait,
+                ));
+            }
+        }
+
+        self.in_flight.fetch_add(1, Ordering::SeqCst);
+
+        Ok(InFlightGuard(self.in_flight.clone()))
+    }
+
+    /// Returns the token once the request is rejected by another limit
+    fn give_back(&mut self) {
+        if let Some(bucket) = self.bucket.as_mut() {
+            bucket.give_back();
+        }
+    }
+
+    fn is_idle(&self, now: Instant) -> bool {
+        self.in_flight.load(Ordering::SeqCst) == 0
+            && now.duration_since(self.last_used) >= PER_IP_CLEANUP_INTERVAL
+            && self
+                .bucket
+                .as_ref()
+                .map(|bucket| bucket.is_full())
+                .unwrap_or(true)
+    }
+}
+
+struct InFlightGuard(Arc<AtomicUsize>);
+
+impl Drop for InFlightGuard {
+    fn drop(&mut self) {
+        self.0.fetch_sub(1, Ordering::SeqCst);
+    }
+}
+
+struct PerIpCounters {
+    counters: HashMap<IpAddr, LimitCounter>,
+    cleaned_up: Instant,
+}
+
+/// Request is counted as concurrent until the permit is dropped
+pub struct GrpcServerLimiterPermit {
+    _in_flight: Vec<InFlightGuard>,
+}
+
+type BoxedStream<TItem> = Pin<Box<dyn futures::Stream<Item = TItem> + Send + Sync + 'static>>;
+
+impl GrpcServerLimiterPermit {
+    /// Streaming rpc is counted as concurrent until the response stream is dropped
+    pub fn keep_with_stream<TItem: 'static>(
+        self,
+        stream: BoxedStream<TItem>,
+    ) -> BoxedStream<TItem> {
+        Box::pin(StreamWithPermit {
+            stream,
+            _permit: self,
+        })
+    }
+}
+
+struct StreamWithPermit<TItem> {
+    stream: BoxedStream<TItem>,
+    _permit: GrpcServerLimiterPermit,
+}
+
+impl<TItem> futures::Stream for StreamWithPermit<TItem> {
+    type Item = TItem;
+
+    fn poll_next(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<TItem>> {
+        self.get_mut().stream.as_mut().poll_next(cx)
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        self.stream.size_hint()
+    }
+}
+
+/// Limits of the rpc (or of the whole service) and of each client ip.
+/// Rejected requests get `RESOURCE_EXHAUSTED` with `RetryInfo` and `QuotaFailure` details
+pub struct GrpcServerLimiter {
+    name: String,
+    limits: GrpcServerLimits,
+    per_ip_limits: GrpcServerLimits,
+    retry_delay: Duration,
+    counter: Mutex<LimitCounter>,
+    per_ip: Mutex<PerIpCounters>,
+}
+
+impl GrpcServerLimiter {
+    /// Name is used as a subject of the quota violation. Usually it is the rpc name
+    pub fn new(name: impl Into<String>) -> Self {
+        let limits = GrpcServerLimits::default();
+        Self {
+            name: name.into(),
+            counter: Mutex::new(LimitCounter::new(&limits)),
+            limits,
+            per_ip_limits: GrpcServerLimits::default(),
+            retry_delay: DEFAULT_SERVER_LIMIT_RETRY_DELAY,
+            per_ip: Mutex::new(PerIpCounters {
+                counters: HashMap::new(),
+                cleaned_up: Instant::now(),
+            }),
+        }
+    }
+
+    pub fn with_limits(mut self, limits: GrpcServerLimits) -> Self {
+        self.counter = Mutex::new(LimitCounter::new(&limits));
+        self.limits = limits;
+        self
+    }
+
+    /// Limits applied to each client ip separately
+    pub fn with_per_ip_limits(mut self, per_ip_limits: GrpcServerLimits) -> Self {
+        self.per_ip_limits = per_ip_limits;
+        self
+    }
+
+    /// Retry delay sent to the client once max_concurrent is reached.
+    /// Once max_requests_per_second is reached the delay is the time until the next token
+    pub fn with_retry_delay(mut self, retry_delay: Duration) -> Self {
+        self.retry_delay = retry_delay;
+        self
+    }
+
+    /// Requests without remote addr are limited by the limits of the rpc only
+    pub fn try_acquire(
+        &self,
+        remote_addr: Option<SocketAddr>,
+    ) -> Result<GrpcServerLimiterPermit, tonic::Status> {
+        let mut in_flight = Vec::with_capacity(2);
+
+        let ip = self.get_limited_ip(remote_addr);
+
+        if let Some(ip) = ip {
+            let guard = self.acquire_per_ip(ip).map_err(|(description, delay)| {
+                self.create_status(format!("{} ip:{}", self.name, ip), description, delay)
+            })?;
+            in_flight.push(guard);
+        }
+
+        if !self.limits.is_empty() {
+            let result = self
+                .counter
+                .lock()
+                .unwrap()
+                .try_acquire(&self.limits, self.retry_delay);
+
+            match result {
+                Ok(guard) => in_flight.push(guard),
+                Err((description, delay)) => {
+                    if let Some(ip) = ip {
+                        self.give_back_per_ip(ip);
+                    }
+
+                    return Err(self.create_status(self.name.clone(), description, delay));
+                }
+            }
+        }
+
+        Ok(GrpcServerLimiterPermit {
+            _in_flight: in_flight,
+        })
+    }
+
+    /// Returns the tokens taken by successful `try_acquire` once the request is rejected by another limiter
+    pub fn give_back(&self, remote_addr: Option<SocketAddr>) {
+        if !self.limits.is_empty() {
+            self.counter.lock().unwrap().give_back();
+        }
+
+        if let Some(ip) = self.get_limited_ip(remote_addr) {
+            self.give_back_per_ip(ip);
+        }
+    }
+
+    fn get_limited_ip(&self, remote_addr: Option<SocketAddr>) -> Option<IpAddr> {
+        if self.per_ip_limits.is_empty() {
+            return None;
+        }
+
+        remote_addr.map(|remote_addr| remote_addr.ip())
+    }
+
+    fn give_back_per_ip(&self, ip: IpAddr) {
+        if let Some(counter) = self.per_ip.lock().unwrap().counters.get_mut(&ip) {
+            counter.give_back();
+        }
+    }
+
+    fn acquire_per_ip(&self, ip: IpAddr) -> Result<InFlightGuard, (String, Duration)> {
+        let now = Instant::now();
+        let mut per_ip = self.per_ip.lock().unwrap();
+
+        if now.duration_since(per_ip.cleaned_up) >= PER_IP_CLEANUP_INTERVAL {
+            per_ip.counters.retain(|_, counter| !counter.is_idle(now));
+            per_ip.cleaned_up = now;
+        }
+
+        per_ip
+            .counters
+            .entry(ip)
+            .or_insert_with(|| LimitCounter::new(&self.per_ip_limits))
+            .try_acquire(&self.per_ip_limits, self.retry_delay)
+    }
+
+    fn create_status(
+        &self,
+        subject: String,
+        description: String,
+        delay: Duration,
+    ) -> tonic::Status {
+        let mut details = ErrorDetails::with_retry_info(Some(delay));
+        details.add_quota_failure_violation(subject.as_str(), description.as_str());
+
+        tonic::Status::with_error_details(
+            tonic::Code::ResourceExhausted,
+            format!("Too many requests. {}: {}", subject, description),
+            details,
+        )
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::net::SocketAddr;
+
+    use tonic_types::StatusExt;
+
+    use super::*;
+
+    #[test]
+    fn test_server_limits() {
+        let rt = tokio::runtime::Builder::new_current_thread()
+            .enable_time()
+            .build()
+            .unwrap();
+
+        rt.block_on(async {
+            let limiter = GrpcServerLimiter::new("Get")
+                .with_limits(GrpcServerLimits::new().with_max_concurrent(2))
+                .with_per_ip_limits(GrpcServerLimits::new().with_max_requests_per_second(1));
+
+            let addr_1: SocketAddr = "10.0.0.1:5000".parse().unwrap();
+            let addr_2: SocketAddr = "10.0.0.2:5000".parse().unwrap();
+
+            let permit_1 = limiter.try_acquire(Some(addr_1)).unwrap();
+
+            let status = limiter.try_acquire(Some(addr_1)).err().unwrap();
+            assert_eq!(status.code(), tonic::Code::ResourceExhausted);
+            assert!(status
+                .get_details_retry_info()
+                .unwrap()
+                .retry_delay
+                .is_some());
+
+            let _permit_2 = limiter.try_acquire(Some(addr_2)).unwrap();
+
+            let status = limiter.try_acquire(None).err().unwrap();
+            assert_eq!(status.code(), tonic::Code::ResourceExhausted);
+            assert_eq!(
+                status.get_details_retry_info().unwrap().retry_delay,
+                Some(DEFAULT_SERVER_LIMIT_RETRY_DELAY)
+            );
+            assert_eq!(
+                status.get_details_quota_failure().unwrap().violations[0].subject,
+                "Get"
+            );
+
+            drop(permit_1);
+            assert!(limiter.try_acquire(None).is_ok());
+        });
+    }
+
+    #[test]
+    fn test_per_ip_token_is_given_back_once_rpc_limit_rejects() {
+        let rt = tokio::runtime::Builder::new_current_thread()
+            .enable_time()
+            .build()
+            .unwrap();
+
+        rt.block_on(async {
+            let limiter = GrpcServerLimiter::new("Get")
+                .with_limits(GrpcServerLimits::new().with_max_concurrent(1))
+                .with_per_ip_limits(GrpcServerLimits::new().with_max_requests_per_second(2));
+
+            let addr_1: SocketAddr = "10.0.0.1:5000".parse().unwrap();
+            let addr_2: SocketAddr = "10.0.0.2:5000".parse().unwrap();
+
+            let permit = limiter.try_acquire(Some(addr_2)).unwrap();
+
+            for _ in 0..5 {
+                let status = limiter.try_acquire(Some(addr_1)).err().unwrap();
+                assert_eq!(
+                    status.get_details_quota_failure().unwrap().violations[0].subject,
+                    "Get"
+                );
+            }
+
+            drop(permit);
+            assert!(limiter.try_acquire(Some(addr_1)).is_ok());
+        });
+    }
+
+    #[test]
+    fn test_stream_is_counted_until_dropped() {
+        let limiter = GrpcServerLimiter::new("Subscribe")
+            .with_limits(GrpcServerLimits::new().with_max_concurrent(1));
+
+        let permit = limiter.try_acquire(None).unwrap();
+        let stream: BoxedStream<i32> = Box::pin(futures::stream::pending());
+        let stream = permit.keep_with_stream(stream);
+
+        let status = limiter.try_acquire(None).err().unwrap();
+        assert_eq!(status.code(), tonic::Code::ResourceExhausted);
+
+        drop(stream);
+        assert!(limiter.try_acquire(None).is_ok());
+    }
+}
@@ -1,12 +1,23 @@
-use std::{fmt::Debug, pin::Pin, time::Duration};
+use std::{fmt::Debug, pin::Pin};
 
-use tokio::sync::mpsc::error::SendTimeoutError;
-const DEFAULT_SEND_TIMEOUT: Duration = Duration::from_secs(30);
+use tokio::sync::mpsc::Receiver;
+
+use crate::{IntoGrpcStatus, ServerStreamOptions, ServerStreamSender};
 
 pub type SendStream<TDest> = tonic::Response<
     Pin<Box<dyn futures_util::Stream<Item = Result<TDest, tonic::Status>> + Send + Sync + 'static>>,
 >;
 
+fn into_send_stream<TDest: Send + Sync + 'static>(
+    rx: Receiver<Result<TDest, tonic::Status>>,
+) -> SendStream<TDest> {
+    let output_stream = tokio_stream::wrappers::ReceiverStream::new(rx);
+    let response: Pin<
+        Box<dyn futures::Stream<Item = Result<TDest, tonic::Status>> + Send + Sync + 'static>,
+    > = Box::pin(output_stream);
+    tonic::Response::new(response)
+}
+
 pub async fn create_empty_stream<TDest>() -> Result<
     tonic::Response<
         Pin<
@@ -50,32 +61,15 @@ pub async fn send_single_item_to_stream<TDest>(
 where
     TDest: Send + Sync + Debug + 'static,
 {
-    let (tx, rx) = tokio::sync::mpsc::channel(1);
+    let options = ServerStreamOptions::default().with_channel_size(1);
+    let (tx, rx) = tokio::sync::mpsc::channel(options.get_channel_capacity());
 
     tokio::spawn(async move {
-        let send_timeout = DEFAULT_SEND_TIMEOUT;
-
-        let sent_result = tx
-            .send_timeout(Result::<_, tonic::Status>::Ok(item), send_timeout)
-            .await;
-
-        if let Err(err) = sent_result {
-            match err {
-                SendTimeoutError::Timeout(err) => {
-                    println!("Can not send to grpc channel. Timeout. Err: {:?}", err);
-                }
-                SendTimeoutError::Closed(err) => {
-                    println!("Can not send to grpc channel. Its closed. Err: {:?}", err);
-                }
-            }
-        }
+        let sender = ServerStreamSender::new(tx, options);
+        let _ = sender.send(item).await;
     });
 
-    let output_stream = tokio_stream::wrappers::ReceiverStream::new(rx);
-    let response: Pin<
-        Box<dyn futures::Stream<Item = Result<TDest, tonic::Status>> + Send + Sync + 'static>,
-    > = Box::pin(output_stream);
-    return Ok(tonic::Response::new(response));
+    Ok(into_send_stream(rx))
 }
 
 #[deprecated(
@@ -124,40 +118,32 @@ where
     TSrc: Send + Sync + Debug + 'static,
     TDest: Send + Sync + Debug + 'static,
 {
-    let channel_size = 100;
+    send_from_iterator_with_transformation_and_options(src, mapping, ServerStreamOptions::default())
+        .await
+}
 
-    let (tx, rx) = tokio::sync::mpsc::channel(channel_size);
+pub async fn send_from_iterator_with_transformation_and_options<TSrc, TDest>(
+    src: impl Iterator<Item = TSrc> + Send + Sync + 'static,
+    mapping: impl Fn(TSrc) -> TDest + Send + Sync + 'static,
+    options: ServerStreamOptions,
+) -> Result<SendStream<TDest>, tonic::Status>
+where
+    TSrc: Send + Sync + Debug + 'static,
+    TDest: Send + Sync + Debug + 'static,
+{
+    let (tx, rx) = tokio::sync::mpsc::channel(options.get_channel_capacity());
 
     tokio::spawn(async move {
-        let send_timeout = DEFAULT_SEND_TIMEOUT;
+        let sender = ServerStreamSender::new(tx, options);
 
         for itm in src {
-            let contract = mapping(itm);
-
-            let sent_result = tx
-                .send_timeout(Result::<_, tonic::Status>::Ok(contract), send_timeout)
-                .await;
-
-            if let Err(err) = sent_result {
-                match err {
-                    SendTimeoutError::Timeout(err) => {
-                        println!("Can not send to grpc channel. Timeout. Err: {:?}", err);
-                        break;
-                    }
-                    SendTimeoutError::Closed(err) => {
-                        println!("Can not send to grpc channel. Its closed. Err: {:?}", err);
-                        break;
-                    }
-                }
+            if sender.send(mapping(itm)).await.is_err() {
+                break;
             }
         }
     });
 
-    let output_stream = tokio_stream::wrappers::ReceiverStream::new(rx);
-    let response: Pin<
-        Box<dyn futures::Stream<Item = Result<TDest, tonic::Status>> + Send + Sync + 'static>,
-    > = Box::pin(output_stream);
-    return Ok(tonic::Response::new(response));
+    Ok(into_send_stream(rx))
 }
 
 pub async fn send_from_iterator<TSrc, TDest: From<TSrc>>(
@@ -179,40 +165,131 @@ where
     TSrc: Send + Sync + Debug + 'static,
     TDest: From<TSrc> + Send + Sync + Debug + 'static,
 {
-    let channel_size = 100;
+    send_from_iterator_with_options(src, ServerStreamOptions::default()).await
+}
 
-    let (tx, rx) = tokio::sync::mpsc::channel(channel_size);
+pub async fn send_from_iterator_with_options<TSrc, TDest: From<TSrc>>(
+    src: impl Iterator<Item = TSrc> + Send + Sync + 'static,
+    options: ServerStreamOptions,
+) -> Result<SendStream<TDest>, tonic::Status>
+where
+    TSrc: Send + Sync + Debug + 'static,
+    TDest: From<TSrc> + Send + Sync + Debug + 'static,
+{
+    send_from_iterator_with_transformation_and_options(src, |itm| itm.into(), options).await
+}
 
-    tokio::spawn(async move {
-        let send_timeout = DEFAULT_SEND_TIMEOUT;
+/// Items are pulled lazily from the source. Reading stops once the client disconnects.
+/// Error of the source is sent to the client as a status and the stream is closed.
+pub async fn send_from_stream<TSrc, TDest, TErr>(
+    src: impl futures::Stream<Item = Result<TSrc, TErr>> + Send + 'static,
+    options: ServerStreamOptions,
+) -> Result<SendStream<TDest>, tonic::Status>
+where
+    TSrc: Send + 'static,
+    TDest: From<TSrc> + Send + Sync + Debug + 'static,
+    TErr: IntoGrpcStatus + Send + 'static,
+{
+    send_from_stream_with_transformation(src, |itm| itm.into(), options).await
+}
 
-        for itm in src {
-            let contract: TDest = itm.into();
+pub async fn send_from_stream_with_transformation<TSrc, TDest, TErr>(
+    src: impl futures::Stream<Item = Result<TSrc, TErr>> + Send + 'static,
+    mapping: impl Fn(TSrc) -> TDest + Send + Sync + 'static,
+    options: ServerStreamOptions,
+) -> Result<SendStream<TDest>, tonic::Status>
+where
+    TSrc: Send + 'static,
+    TDest: Send + Sync + Debug + 'static,
+    TErr: IntoGrpcStatus + Send + 'static,
+{
+    let (tx, rx) = tokio::sync::mpsc::channel(options.get_channel_capacity());
+
+    tokio::spawn(async move {
+        let sender = ServerStreamSender::new(tx, options);
 
-            let sent_result = tx
-                .send_timeout(Result::<_, tonic::Status>::Ok(contract), send_timeout)
-                .await;
+        let mut src = Box::pin(src);
 
-            if let Err(err) = sent_result {
-                match err {
-                    SendTimeoutError::Timeout(err) => {
-                        println!("Can not send to grpc channel. Timeout. Err: {:?}", err);
+        while let Some(itm) = sender.get_next(&mut src).await {
+            match itm {
+                Ok(itm) => {
+                    if sender.send(mapping(itm)).await.is_err() {
                         break;
                     }
-                    SendTimeoutError::Closed(err) => {
-                        println!("Can not send to grpc channel. Its closed. Err: {:?}", err);
-                        break;
+                }
+                Err(err) => {
+                    sender.send_error(err.into_grpc_status()).await;
+                    break;
+                }
+            }
+        }
+    });
+
+    Ok(into_send_stream(rx))
+}
+
+pub async fn send_from_stream_by_chunks<TSrc, TDest, TDestChunk, TErr>(
+    src: impl futures::Stream<Item = Result<TSrc, TErr>> + Send + 'static,
+    chunk_size: usize,
+    mapping: impl Fn(TSrc) -> TDest + Send + Sync + 'static,
+    transform_to_contract: impl Fn(Vec<TDest>) -> TDestChunk + Send + Sync + 'static,
+    options: ServerStreamOptions,
+) -> Result<SendStream<TDestChunk>, tonic::Status>
+where
+    TSrc: Send + 'static,
+    TDest: Send + Sync + Debug + 'static,
+    TDestChunk: Send + Sync + Debug + 'static,
+    TErr: IntoGrpcStatus + Send + 'static,
+{
+    let (tx, rx) = tokio::sync::mpsc::channel(options.get_channel_capacity());
+
+    tokio::spawn(async move {
+        let sender = ServerStreamSender::new(tx, options);
+
+        let mut src = Box::pin(src);
+
+        let mut chunk: Vec<TDest> = Vec::with_capacity(chunk_size);
+
+        while let Some(itm) = sender.get_next(&mut src).await {
+            match itm {
+                Ok(itm) => {
+                    chunk.push(mapping(itm));
+                }
+                Err(err) => {
+                    if chunk.len() > 0 {
+                        let contract = transform_to_contract(chunk);
+                        if sender.send(contract).await.is_err() {
+                            return;
+                        }
                     }
+
+                    sender.send_error(err.into_grpc_status()).await;
+                    return;
                 }
             }
+
+            if chunk.len() < chunk_size {
+                continue;
+            }
+
+            let mut chunk_to_send = Vec::with_capacity(chunk_size);
+
+            std::mem::swap(&mut chunk, &mut chunk_to_send);
+
+            let contract = transform_to_contract(chunk_to_send);
+
+            if sender.send(contract).await.is_err() {
+                return;
+            }
+        }
+
+        if chunk.len() > 0 && !sender.is_closed() {
+            let contract = transform_to_contract(chunk);
+            let _ = sender.send(contract).await;
         }
     });
 
-    let output_stream = tokio_stream::wrappers::ReceiverStream::new(rx);
-    let response: Pin<
-        Box<dyn futures::Stream<Item = Result<TDest, tonic::Status>> + Send + Sync + 'static>,
-    > = Box::pin(output_stream);
-    return Ok(tonic::Response::new(response));
+    Ok(into_send_stream(rx))
 }
 
 pub async fn send_vec_to_stream_by_chunks<TSrc, TDest, TDestChunk>(
@@ -238,12 +315,32 @@ where
     TDest: Send + Sync + Debug + 'static,
     TDestChunk: Send + Sync + Debug + 'static,
 {
-    let channel_size = 100;
+    send_vec_to_stream_by_chunks_with_options(
+        src,
+        chunk_size,
+        mapping,
+        transform_to_contract,
+        ServerStreamOptions::default(),
+    )
+    .await
+}
 
-    let (tx, rx) = tokio::sync::mpsc::channel(channel_size);
+pub async fn send_vec_to_stream_by_chunks_with_options<TSrc, TDest, TDestChunk>(
+    src: Vec<TSrc>,
+    chunk_size: usize,
+    mapping: impl Fn(TSrc) -> TDest + Send + Sync + 'static,
+    transform_to_contract: impl Fn(Vec<TDest>) -> TDestChunk + Send + Sync + 'static,
+    options: ServerStreamOptions,
+) -> Result<SendStream<TDestChunk>, tonic::Status>
+where
+    TSrc: Send + Sync + 'static,
+    TDest: Send + Sync + Debug + 'static,
+    TDestChunk: Send + Sync + Debug + 'static,
+{
+    let (tx, rx) = tokio::sync::mpsc::channel(options.get_channel_capacity());
 
     tokio::spawn(async move {
-        let send_timeout = DEFAULT_SEND_TIMEOUT;
+        let sender = ServerStreamSender::new(tx, options);
 
         let mut chunk: Vec<TDest> = Vec::with_capacity(chunk_size);
 
@@ -260,48 +357,18 @@ where
 
             let contract = transform_to_contract(chunk_to_send);
 
-            let sent_result = tx
-                .send_timeout(Result::<_, tonic::Status>::Ok(contract), send_timeout)
-                .await;
-
-            if let Err(err) = sent_result {
-                match err {
-                    SendTimeoutError::Timeout(err) => {
-                        println!("Can not send to grpc channel. Timeout. Err: {:?}", err);
-                        break;
-                    }
-                    SendTimeoutError::Closed(err) => {
-                        println!("Can not send to grpc channel. Its closed. Err: {:?}", err);
-                        break;
-                    }
-                }
+            if sender.send(contract).await.is_err() {
+                return;
             }
         }
 
         if chunk.len() > 0 {
             let contract = transform_to_contract(chunk);
-            let sent_result = tx
-                .send_timeout(Result::<_, tonic::Status>::Ok(contract), send_timeout)
-                .await;
-
-            if let Err(err) = sent_result {
-                match err {
-                    SendTimeoutError::Timeout(err) => {
-                        println!("Can not send to grpc channel. Timeout. Err: {:?}", err);
-                    }
-                    SendTimeoutError::Closed(err) => {
-                        println!("Can not send to grpc channel. Its closed. Err: {:?}", err);
-                    }
-                }
-            }
+            let _ = sender.send(contract).await;
         }
     });
 
-    let output_stream = tokio_stream::wrappers::ReceiverStream::new(rx);
-    let response: Pin<
-        Box<dyn futures::Stream<Item = Result<TDestChunk, tonic::Status>> + Send + Sync + 'static>,
-    > = Box::pin(output_stream);
-    return Ok(tonic::Response::new(response));
+    Ok(into_send_stream(rx))
 }
 
 pub async fn send_hash_map_to_stream<TKeySrc, TValueSrc, TDest>(
@@ -325,36 +392,30 @@ where
     TValueSrc: Send + Sync + 'static,
     TDest: Send + Sync + Debug + 'static,
 {
-    let channel_size = 100;
-    let (tx, rx) = tokio::sync::mpsc::channel(channel_size);
+    send_hash_map_to_stream_with_options(src, mapping, ServerStreamOptions::default()).await
+}
 
-    tokio::spawn(async move {
-        let send_timeout = DEFAULT_SEND_TIMEOUT;
-        for (key, value) in src {
-            let contract = mapping(key, value);
+pub async fn send_hash_map_to_stream_with_options<TKeySrc, TValueSrc, TDest>(
+    src: std::collections::HashMap<TKeySrc, TValueSrc>,
+    mapping: impl Fn(TKeySrc, TValueSrc) -> TDest + Send + Sync + 'static,
+    options: ServerStreamOptions,
+) -> Result<SendStream<TDest>, tonic::Status>
+where
+    TKeySrc: Send + Sync + 'static,
+    TValueSrc: Send + Sync + 'static,
+    TDest: Send + Sync + Debug + 'static,
+{
+    let (tx, rx) = tokio::sync::mpsc::channel(options.get_channel_capacity());
 
-            let sent_result = tx
-                .send_timeout(Result::<_, tonic::Status>::Ok(contract), send_timeout)
-                .await;
+    tokio::spawn(async move {
+        let sender = ServerStreamSender::new(tx, options);
 
-            if let Err(err) = sent_result {
-                match err {
-                    SendTimeoutError::Timeout(err) => {
-                        println!("Can not send to grpc channel. Timeout. Err: {:?}", err);
-                        break;
-                    }
-                    SendTimeoutError::Closed(err) => {
-                        println!("Can not send to grpc channel. Its closed. Err: {:?}", err);
-                        break;
-                    }
-                }
+        for (key, value) in src {
+            if sender.send(mapping(key, value)).await.is_err() {
+                break;
             }
         }
     });
 
-    let output_stream = tokio_stream::wrappers::ReceiverStream::new(rx);
-    let response: Pin<
-        Box<dyn futures::Stream<Item = Result<TDest, tonic::Status>> + Send + Sync + 'static>,
-    > = Box::pin(output_stream);
-    return Ok(tonic::Response::new(response));
+    Ok(into_send_stream(rx))
 }
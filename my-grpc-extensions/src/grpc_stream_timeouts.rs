@@ -0,0 +1,186 @@
+use std::time::Duration;
+
+use futures_util::StreamExt;
+use tokio::time::Instant;
+
+use crate::GrpcReadError;
+
+/// Timeouts applied while reading the server stream.
+/// `Duration` converts into the idle timeout without deadline, which is the legacy behavior.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct GrpcStreamTimeouts {
+    /// Max gap between two items. `None` - stream can be idle forever (subscriptions)
+    pub idle_timeout: Option<Duration>,
+    /// Max duration of the whole stream starting from the moment it is opened
+    pub deadline: Option<Duration>,
+}
+
+impl GrpcStreamTimeouts {
+    pub fn new(idle_timeout: Duration) -> Self {
+        Self {
+            idle_timeout: Some(idle_timeout),
+            deadline: None,
+        }
+    }
+
+    pub fn without_idle_timeout() -> Self {
+        Self {
+            idle_timeout: None,
+            deadline: None,
+        }
+    }
+
+    pub fn with_idle_timeout(mut self, idle_timeout: Option<Duration>) -> Self {
+        self.idle_timeout = idle_timeout;
+        self
+    }
+
+    pub fn with_deadline(mut self, deadline: Duration) -> Self {
+        self.deadline = Some(deadline);
+        self
+    }
+}
+
+impl From<Duration> for GrpcStreamTimeouts {
+    fn from(idle_timeout: Duration) -> Self {
+        Self::new(idle_timeout)
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) enum StreamTimeout {
+    Idle,
+    Deadline,
+}
+
+impl StreamTimeout {
+    pub fn into_error(self) -> GrpcReadError {
+        match self {
+            StreamTimeout::Idle => GrpcReadError::Timeout,
+            StreamTimeout::Deadline => GrpcReadError::DeadlineExceeded,
+        }
+    }
+}
+
+/// Deadline is counted from the moment timer is created
+pub(crate) struct StreamReadTimer {
+    idle_timeout: Option<Duration>,
+    deadline: Option<Instant>,
+}
+
+impl StreamReadTimer {
+    pub fn new(timeouts: GrpcStreamTimeouts) -> Self {
+        Self {
+            idle_timeout: timeouts.idle_timeout,
+            deadline: timeouts.deadline.map(|deadline| Instant::now() + deadline),
+        }
+    }
+
+    pub fn set_idle_timeout(&mut self, idle_timeout: Option<Duration>) {
+        self.idle_timeout = idle_timeout;
+    }
+
+    pub fn set_deadline(&mut self, deadline: Option<Duration>) {
+        self.deadline = deadline.map(|deadline| Instant::now() + deadline);
+    }
+
+    pub fn get_idle_timeout(&self) -> Option<Duration> {
+        self.idle_timeout
+    }
+
+    /// Timeouts left for the rest of the stream
+    pub fn get_remaining_timeouts(&self) -> GrpcStreamTimeouts {
+        GrpcStreamTimeouts {
+            idle_timeout: self.idle_timeout,
+            deadline: self
+                .deadline
+                .map(|deadline| deadline.saturating_duration_since(Instant::now())),
+        }
+    }
+
+    /// Moment the next item has to be received before. `None` - wait forever
+    pub fn get_next_item_wake_up(&self) -> Option<(Instant, StreamTimeout)> {
+        let idle = self
+            .idle_timeout
+            .map(|idle_timeout| (Instant::now() + idle_timeout, StreamTimeout::Idle));
+
+        let deadline = self
+            .deadline
+            .map(|deadline| (deadline, StreamTimeout::Deadline));
+
+        match (idle, deadline) {
+            (Some(idle), Some(deadline)) => {
+                if deadline.0 <= idle.0 {
+                    Some(deadline)
+                } else {
+                    Some(idle)
+                }
+            }
+            (idle, deadline) => idle.or(deadline),
+        }
+    }
+
+    pub async fn read_next<T>(
+        &self,
+        stream_to_read: &mut tonic::Streaming<T>,
+    ) -> Result<Option<T>, GrpcReadError> {
+        let response = match self.get_next_item_wake_up() {
+            Some((wake_up, timeout)) => {
+                match tokio::time::timeout_at(wake_up, stream_to_read.next()).await {
+                    Ok(response) => response,
+                    Err(_) => return Err(timeout.into_error()),
+                }
+            }
+            None => stream_to_read.next().await,
+        };
+
+        match response {
+            Some(item) => Ok(Some(item?)),
+            None => Ok(None),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::Duration;
+
+    use super::*;
+
+    #[test]
+    fn test_next_item_wake_up_picks_closest_timeout() {
+        let rt = tokio::runtime::Builder::new_current_thread()
+            .enable_time()
+            .build()
+            .unwrap();
+
+        rt.block_on(async {
+            let timer = StreamReadTimer::new(Duration::from_secs(5).into());
+            assert_eq!(
+                timer.get_next_item_wake_up().unwrap().1,
+                StreamTimeout::Idle
+            );
+
+            let timer = StreamReadTimer::new(
+                GrpcStreamTimeouts::new(Duration::from_secs(5))
+                    .with_deadline(Duration::from_secs(1)),
+            );
+            assert_eq!(
+                timer.get_next_item_wake_up().unwrap().1,
+                StreamTimeout::Deadline
+            );
+
+            let timer = StreamReadTimer::new(GrpcStreamTimeouts::without_idle_timeout());
+            assert!(timer.get_next_item_wake_up().is_none());
+
+            let timer = StreamReadTimer::new(
+                GrpcStreamTimeouts::without_idle_timeout().with_deadline(Duration::from_secs(1)),
+            );
+            assert_eq!(
+                timer.get_next_item_wake_up().unwrap().1,
+                StreamTimeout::Deadline
+            );
+            assert!(timer.get_remaining_timeouts().deadline.unwrap() <= Duration::from_secs(1));
+        });
+    }
+}
@@ -0,0 +1,143 @@
+use std::{collections::HashMap, future::Future, sync::Arc, time::Duration};
+
+use tokio::{
+    sync::{Mutex, OnceCell},
+    time::Instant,
+};
+
+/// Idempotency key bound to the encoded request, so the key reused with another payload is executed as a new request
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct IdempotencyCacheKey {
+    key: String,
+    payload: Vec<u8>,
+}
+
+impl IdempotencyCacheKey {
+    pub fn new(key: String, payload: Vec<u8>) -> Self {
+        Self { key, payload }
+    }
+
+    pub fn from_request<T: prost::Message>(request: &tonic::Request<T>) -> Option<Self> {
+        let key = crate::get_idempotency_key(request)?;
+        Some(Self::new(key, request.get_ref().encode_to_vec()))
+    }
+}
+
+struct IdempotencyCacheItem<T> {
+    created: Instant,
+    value: Arc<OnceCell<T>>,
+}
+
+struct IdempotencyCacheItems<T> {
+    items: HashMap<IdempotencyCacheKey, IdempotencyCacheItem<T>>,
+    last_eviction: Instant,
+}
+
+/// Keeps responses by idempotency key, so duplicated requests get the response of the first execution
+pub struct IdempotencyCache<T: Clone + Send + Sync + 'static> {
+    ttl: Duration,
+    items: Mutex<IdempotencyCacheItems<T>>,
+}
+
+impl<T: Clone + Send + Sync + 'static> IdempotencyCache<T> {
+    pub fn new(ttl: Duration) -> Self {
+        Self {
+            ttl,
+            items: Mutex::new(IdempotencyCacheItems {
+                items: HashMap::new(),
+                last_eviction: Instant::now(),
+            }),
+        }
+    }
+
+    pub async fn get_or_execute(
+        &self,
+        idempotency_key: Option<IdempotencyCacheKey>,
+        execute: impl Future<Output = T>,
+    ) -> T {
+        let Some(idempotency_key) = idempotency_key else {
+            return execute.await;
+        };
+
+        let value = self.get_cell(idempotency_key).await;
+
+        value.get_or_init(|| execute).await.clone()
+    }
+
+    /// Errors are not cached, so next request with the same key executes the handler again
+    pub async fn get_or_try_execute(
+        &self,
+        idempotency_key: Option<IdempotencyCacheKey>,
+        execute: impl Future<Output = Result<T, tonic::Status>>,
+    ) -> Result<T, tonic::Status> {
+        let Some(idempotency_key) = idempotency_key else {
+            return execute.await;
+        };
+
+        let value = self.get_cell(idempotency_key).await;
+
+        let result = value.get_or_try_init(|| execute).await?;
+        Ok(result.clone())
+    }
+
+    async fn get_cell(&self, idempotency_key: IdempotencyCacheKey) -> Arc<OnceCell<T>> {
+        let now = Instant::now();
+        let mut items = self.items.lock().await;
+
+        // Expired items are evicted once per ttl, expired item found before that is replaced on lookup
+        if now.duration_since(items.last_eviction) >= self.ttl {
+            items
+                .items
+                .retain(|_, itm| now.duration_since(itm.created) < self.ttl);
+            items.last_eviction = now;
+        }
+
+        let item = items
+            .items
+            .entry(idempotency_key)
+            .or_insert_with(|| IdempotencyCacheItem {
+                created: now,
+                value: Arc::new(OnceCell::new()),
+            });
+
+        if now.duration_since(item.created) >= self.ttl {
+            item.created = now;
+            item.value = Arc::new(OnceCell::new());
+        }
+
+        item.value.clone()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_key_is_bound_to_payload_and_expires() {
+        let rt = tokio::runtime::Builder::new_current_thread()
+            .enable_time()
+            .build()
+            .unwrap();
+
+        rt.block_on(async {
+            let cache = IdempotencyCache::new(Duration::from_millis(50));
+
+            let key = |payload: &[u8]| {
+                Some(IdempotencyCacheKey::new(
+                    "key".to_string(),
+                    payload.to_vec(),
+                ))
+            };
+
+            assert_eq!(cache.get_or_execute(key(b"1"), async { 1 }).await, 1);
+            assert_eq!(cache.get_or_execute(key(b"1"), async { 2 }).await, 1);
+            assert_eq!(cache.get_or_execute(key(b"2"), async { 3 }).await, 3);
+
+            tokio::time::sleep(Duration::from_millis(80)).await;
+
+            assert_eq!(cache.get_or_execute(key(b"1"), async { 4 }).await, 4);
+            assert_eq!(cache.items.lock().await.items.len(), 1);
+        });
+    }
+}
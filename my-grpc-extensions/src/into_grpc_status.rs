@@ -0,0 +1,42 @@
+/// Converts handler errors into `tonic::Status` which is returned to the grpc client
+pub trait IntoGrpcStatus {
+    fn into_grpc_status(self) -> tonic::Status;
+}
+
+impl IntoGrpcStatus for tonic::Status {
+    fn into_grpc_status(self) -> tonic::Status {
+        self
+    }
+}
+
+impl IntoGrpcStatus for String {
+    fn into_grpc_status(self) -> tonic::Status {
+        tonic::Status::internal(self)
+    }
+}
+
+impl<'s> IntoGrpcStatus for &'s str {
+    fn into_grpc_status(self) -> tonic::Status {
+        tonic::Status::internal(self)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::IntoGrpcStatus;
+
+    #[test]
+    fn test_string_is_converted_to_internal() {
+        let status = "Something went wrong".to_string().into_grpc_status();
+
+        assert_eq!(status.code(), tonic::Code::Internal);
+        assert_eq!(status.message(), "Something went wrong");
+    }
+
+    #[test]
+    fn test_status_is_kept() {
+        let status = tonic::Status::not_found("Not found").into_grpc_status();
+
+        assert_eq!(status.code(), tonic::Code::NotFound);
+    }
+}
@@ -2,6 +2,23 @@ mod grpc_channel;
 #[cfg(feature = "with-telemetry")]
 mod grpc_client_interceptor;
 pub mod grpc_server_streams;
+mod server_stream_options;
+pub use server_stream_options::*;
+mod paged_stream;
+pub use paged_stream::*;
+mod grpc_stream_timeouts;
+pub use grpc_stream_timeouts::*;
+mod stream_read_limits;
+pub use stream_read_limits::*;
+mod grpc_response_cache;
+pub use grpc_response_cache::*;
+mod grpc_client_limiter;
+pub use grpc_client_limiter::*;
+mod token_bucket;
+mod grpc_server_limiter;
+pub use grpc_server_limiter::*;
+mod grpc_server_limit_layer;
+pub use grpc_server_limit_layer::*;
 #[cfg(feature = "with-telemetry")]
 mod grpc_server_telemetry_context;
 #[cfg(feature = "with-telemetry")]
@@ -21,6 +38,7 @@ pub extern crate my_grpc_server_macros as server;
 
 pub extern crate hyper;
 pub extern crate tonic;
+pub extern crate tonic_types;
 #[cfg(feature = "with-ssh")]
 mod ssh;
 #[cfg(feature = "with-ssh")]
@@ -28,10 +46,20 @@ pub use ssh::*;
 #[cfg(feature = "with-ssh")]
 pub extern crate my_ssh;
 
+mod into_grpc_status;
+pub use into_grpc_status::*;
+mod grpc_error_details;
+pub use grpc_error_details::*;
+#[cfg(feature = "grpc-server")]
+mod idempotency_cache;
+#[cfg(feature = "grpc-server")]
+pub use idempotency_cache::*;
 #[cfg(feature = "grpc-server")]
 pub mod server_stream_result;
 mod streamed_response_writer;
 pub use streamed_response_writer::*;
+mod batching_producer;
+pub use batching_producer::*;
 
 mod streamed_request;
 pub use streamed_request::*;
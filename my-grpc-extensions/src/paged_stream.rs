@@ -0,0 +1,84 @@
+use std::{collections::VecDeque, future::Future};
+
+pub struct StreamPage<TItem, TCursor> {
+    pub items: Vec<TItem>,
+    /// None means the page is the last one
+    pub next_cursor: Option<TCursor>,
+}
+
+/// Creates a stream which reads pages lazily: the next page is requested once items of the previous one are consumed.
+/// Can be used as a source of `grpc_server_streams::send_from_stream` for paged db queries or file readers.
+pub fn paged_stream<TItem, TCursor, TErr, TFuture>(
+    first_cursor: TCursor,
+    fetch_page: impl Fn(TCursor) -> TFuture + Send + 'static,
+) -> impl futures::Stream<Item = Result<TItem, TErr>> + Send
+where
+    TItem: Send + 'static,
+    TCursor: Send + 'static,
+    TErr: Send + 'static,
+    TFuture: Future<Output = Result<StreamPage<TItem, TCursor>, TErr>> + Send,
+{
+    futures::stream::unfold(
+        (Some(first_cursor), VecDeque::new(), fetch_page),
+        |(mut cursor, mut buffer, fetch_page)| async move {
+            loop {
+                if let Some(item) = buffer.pop_front() {
+                    return Some((Ok(item), (cursor, buffer, fetch_page)));
+                }
+
+                let current_cursor = cursor.take()?;
+
+                match fetch_page(current_cursor).await {
+                    Ok(page) => {
+                        buffer.extend(page.items);
+                        cursor = page.next_cursor;
+                    }
+                    Err(err) => return Some((Err(err), (None, buffer, fetch_page))),
+                }
+            }
+        },
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use futures::StreamExt;
+
+    use super::StreamPage;
+
+    #[test]
+    fn test_pages_are_read_until_last_one() {
+        let stream = super::paged_stream(0, |page_no: usize| async move {
+            let next_cursor = if page_no < 2 { Some(page_no + 1) } else { None };
+
+            Ok::<_, String>(StreamPage {
+                items: vec![page_no * 10, page_no * 10 + 1],
+                next_cursor,
+            })
+        });
+
+        let result: Vec<_> = futures::executor::block_on(stream.collect());
+
+        let result: Vec<usize> = result.into_iter().map(|itm| itm.unwrap()).collect();
+
+        assert_eq!(result, vec![0, 1, 10, 11, 20, 21]);
+    }
+
+    #[test]
+    fn test_error_stops_the_stream() {
+        let stream = super::paged_stream(0, |page_no: usize| async move {
+            if page_no == 1 {
+                return Err("Db error".to_string());
+            }
+
+            Ok(StreamPage {
+                items: vec![page_no],
+                next_cursor: Some(page_no + 1),
+            })
+        });
+
+        let result: Vec<_> = futures::executor::block_on(stream.collect());
+
+        assert_eq!(result, vec![Ok(0), Err("Db error".to_string())]);
+    }
+}
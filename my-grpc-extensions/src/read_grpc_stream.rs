@@ -1,158 +1,121 @@
-use std::{
-    collections::{BTreeMap, HashMap},
-    time::Duration,
+use std::collections::{BTreeMap, HashMap};
+
+use crate::{
+    GrpcReadError, GrpcStreamTimeouts, StreamReadCounter, StreamReadLimits, StreamReadTimer,
 };
 
-use futures_util::StreamExt;
+/// Reads the whole stream without limits. Use `as_vec_with_limits` for streams of unknown size
+pub async fn as_vec<T, TDest: From<T>>(
+    mut stream_to_read: tonic::Streaming<T>,
+    timeouts: impl Into<GrpcStreamTimeouts>,
+) -> Result<Vec<TDest>, GrpcReadError> {
+    let timer = StreamReadTimer::new(timeouts.into());
+    let mut result = Vec::new();
+
+    while let Some(item) = timer.read_next(&mut stream_to_read).await? {
+        result.push(item.into());
+    }
 
-use crate::GrpcReadError;
+    Ok(result)
+}
 
-pub async fn as_vec<T, TDest: From<T>>(
+/// Stream is cancelled once the limit is exceeded
+pub async fn as_vec_with_limits<T: prost::Message, TDest: From<T>>(
     mut stream_to_read: tonic::Streaming<T>,
-    timeout: Duration,
+    timeouts: impl Into<GrpcStreamTimeouts>,
+    limits: StreamReadLimits,
 ) -> Result<Vec<TDest>, GrpcReadError> {
+    let timer = StreamReadTimer::new(timeouts.into());
+    let mut counter = StreamReadCounter::new(limits);
     let mut result = Vec::new();
 
-    loop {
-        let response = tokio::time::timeout(timeout, stream_to_read.next()).await?;
-
-        match response {
-            Some(item) => match item {
-                Ok(item) => {
-                    result.push(item.into());
-                }
-                Err(err) => Err(GrpcReadError::TonicStatus(err))?,
-            },
-            None => {
-                return Ok(result);
-            }
-        }
+    while let Some(item) = timer.read_next(&mut stream_to_read).await? {
+        counter.add(&item)?;
+        result.push(item.into());
     }
+
+    Ok(result)
 }
 
+/// Reads the whole stream without limits. Use `as_vec_with_limits` for streams of unknown size
 pub async fn as_hash_map<TResult, TSrc, TKey>(
     mut stream_to_read: tonic::Streaming<TSrc>,
     get_key: impl Fn(TSrc) -> (TKey, TResult),
-    timeout: Duration,
+    timeouts: impl Into<GrpcStreamTimeouts>,
 ) -> Result<HashMap<TKey, TResult>, GrpcReadError>
 where
     TKey: std::cmp::Eq + core::hash::Hash + Clone,
 {
+    let timer = StreamReadTimer::new(timeouts.into());
     let mut result = HashMap::new();
 
-    loop {
-        let response = tokio::time::timeout(timeout, stream_to_read.next()).await?;
-
-        match response {
-            Some(item) => match item {
-                Ok(item) => {
-                    let (key, value) = get_key(item);
-                    result.insert(key, value);
-                }
-                Err(err) => Err(GrpcReadError::TonicStatus(err))?,
-            },
-            None => {
-                return Ok(result);
-            }
-        }
+    while let Some(item) = timer.read_next(&mut stream_to_read).await? {
+        let (key, value) = get_key(item);
+        result.insert(key, value);
     }
+
+    Ok(result)
 }
 
+/// Reads the whole stream without limits. Use `as_vec_with_limits` for streams of unknown size
 pub async fn as_b_tree_map<TResult, TSrc, TKey>(
     mut stream_to_read: tonic::Streaming<TSrc>,
     get_key: impl Fn(TSrc) -> (TKey, TResult),
-    timeout: Duration,
+    timeouts: impl Into<GrpcStreamTimeouts>,
 ) -> Result<BTreeMap<TKey, TResult>, GrpcReadError>
 where
     TKey: core::hash::Hash + Ord + Clone,
 {
+    let timer = StreamReadTimer::new(timeouts.into());
     let mut result = BTreeMap::new();
 
-    loop {
-        let response = tokio::time::timeout(timeout, stream_to_read.next()).await?;
-
-        match response {
-            Some(item) => match item {
-                Ok(item) => {
-                    let (key, value) = get_key(item);
-                    result.insert(key, value);
-                }
-                Err(err) => Err(GrpcReadError::TonicStatus(err))?,
-            },
-            None => {
-                return Ok(result);
-            }
-        }
+    while let Some(item) = timer.read_next(&mut stream_to_read).await? {
+        let (key, value) = get_key(item);
+        result.insert(key, value);
     }
+
+    Ok(result)
 }
 
+/// Reads the whole stream without limits. Use `as_vec_with_limits` for streams of unknown size
 pub async fn as_vec_with_transformation<T, TDest, TFn: Fn(T) -> TDest>(
     mut stream_to_read: tonic::Streaming<T>,
-    timeout: Duration,
+    timeouts: impl Into<GrpcStreamTimeouts>,
     transform: &TFn,
 ) -> Result<Vec<TDest>, GrpcReadError> {
+    let timer = StreamReadTimer::new(timeouts.into());
     let mut result = Vec::new();
 
-    loop {
-        let response = tokio::time::timeout(timeout, stream_to_read.next()).await?;
-
-        match response {
-            Some(item) => match item {
-                Ok(item) => {
-                    let item = transform(item);
-                    result.push(item);
-                }
-                Err(err) => Err(GrpcReadError::TonicStatus(err))?,
-            },
-            None => {
-                return Ok(result);
-            }
-        }
+    while let Some(item) = timer.read_next(&mut stream_to_read).await? {
+        let item = transform(item);
+        result.push(item);
     }
+
+    Ok(result)
 }
 
+/// Reads the whole stream without limits. Use `as_vec_with_limits` for streams of unknown size
 pub async fn as_vec_with_transformation_and_filter<T, TDest, TFn: Fn(T) -> Option<TDest>>(
     mut stream_to_read: tonic::Streaming<T>,
-    timeout: Duration,
+    timeouts: impl Into<GrpcStreamTimeouts>,
     transform: &TFn,
 ) -> Result<Vec<TDest>, GrpcReadError> {
+    let timer = StreamReadTimer::new(timeouts.into());
     let mut result = Vec::new();
 
-    loop {
-        let response = tokio::time::timeout(timeout, stream_to_read.next()).await?;
-
-        match response {
-            Some(item) => match item {
-                Ok(item) => {
-                    let item = transform(item);
-                    if let Some(item) = item {
-                        result.push(item);
-                    }
-                }
-                Err(err) => Err(GrpcReadError::TonicStatus(err))?,
-            },
-            None => {
-                return Ok(result);
-            }
+    while let Some(item) = timer.read_next(&mut stream_to_read).await? {
+        if let Some(item) = transform(item) {
+            result.push(item);
         }
     }
+
+    Ok(result)
 }
 
 pub async fn first_or_none<T>(
     mut streaming: tonic::Streaming<T>,
-    timeout: Duration,
+    timeouts: impl Into<GrpcStreamTimeouts>,
 ) -> Result<Option<T>, GrpcReadError> {
-    let response = tokio::time::timeout(timeout, streaming.next()).await?;
-
-    match response {
-        Some(item) => match item {
-            Ok(item) => {
-                return Ok(Some(item));
-            }
-            Err(err) => Err(GrpcReadError::TonicStatus(err))?,
-        },
-        None => {
-            return Ok(None);
-        }
-    }
+    let timer = StreamReadTimer::new(timeouts.into());
+    timer.read_next(&mut streaming).await
 }
@@ -0,0 +1,284 @@
+use std::{
+    sync::{Arc, Mutex},
+    time::Duration,
+};
+
+use futures::{future::Either, StreamExt};
+use my_logger::LogEventCtx;
+use tokio::sync::mpsc::{error::SendTimeoutError, OwnedPermit, Sender};
+
+pub const DEFAULT_SERVER_STREAM_CHANNEL_SIZE: usize = 100;
+pub const DEFAULT_SERVER_STREAM_SEND_TIMEOUT: Duration = Duration::from_secs(30);
+
+/// With `DropClient` and `Error` one slot of the channel is reserved for the terminal status, so it is delivered without waiting
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SlowConsumerPolicy {
+    /// Client gets DEADLINE_EXCEEDED status and the stream is closed once the client does not read items within send timeout
+    DropClient,
+    /// Wait for the client without timeout
+    Wait,
+    /// Client gets RESOURCE_EXHAUSTED status once it does not read items within send timeout
+    Error,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum StreamSendError {
+    Closed,
+    Timeout,
+}
+
+#[derive(Clone)]
+pub struct ServerStreamOptions {
+    pub channel_size: usize,
+    pub send_timeout: Duration,
+    pub slow_consumer_policy: SlowConsumerPolicy,
+    pub on_error: Option<Arc<dyn Fn(StreamSendError) + Send + Sync + 'static>>,
+}
+
+impl Default for ServerStreamOptions {
+    fn default() -> Self {
+        Self {
+            channel_size: DEFAULT_SERVER_STREAM_CHANNEL_SIZE,
+            send_timeout: DEFAULT_SERVER_STREAM_SEND_TIMEOUT,
+            slow_consumer_policy: SlowConsumerPolicy::DropClient,
+            on_error: None,
+        }
+    }
+}
+
+impl ServerStreamOptions {
+    /// Channel size is at least 1
+    pub fn with_channel_size(mut self, channel_size: usize) -> Self {
+        self.channel_size = channel_size.max(1);
+        self
+    }
+
+    pub fn with_send_timeout(mut self, send_timeout: Duration) -> Self {
+        self.send_timeout = send_timeout;
+        self
+    }
+
+    pub fn with_slow_consumer_policy(mut self, slow_consumer_policy: SlowConsumerPolicy) -> Self {
+        self.slow_consumer_policy = slow_consumer_policy;
+        self
+    }
+
+    /// Capacity of the channel of the stream: the slot for the terminal status is added on top of channel size
+    pub(crate) fn get_channel_capacity(&self) -> usize {
+        let channel_size = self.channel_size.max(1);
+
+        match self.slow_consumer_policy {
+            SlowConsumerPolicy::Wait => channel_size,
+            SlowConsumerPolicy::DropClient | SlowConsumerPolicy::Error => channel_size + 1,
+        }
+    }
+
+    /// Callback is called on top of logging once the item can not be delivered to the client
+    pub fn with_error_callback(
+        mut self,
+        on_error: impl Fn(StreamSendError) + Send + Sync + 'static,
+    ) -> Self {
+        self.on_error = Some(Arc::new(on_error));
+        self
+    }
+}
+
+pub(crate) struct ServerStreamSender<TDest> {
+    tx: Sender<Result<TDest, tonic::Status>>,
+    options: ServerStreamOptions,
+    status_permit: Mutex<Option<OwnedPermit<Result<TDest, tonic::Status>>>>,
+}
+
+impl<TDest> ServerStreamSender<TDest> {
+    /// Channel has to be created with `ServerStreamOptions::get_channel_capacity`
+    pub fn new(tx: Sender<Result<TDest, tonic::Status>>, options: ServerStreamOptions) -> Self {
+        let status_permit = match options.slow_consumer_policy {
+            SlowConsumerPolicy::Wait => None,
+            SlowConsumerPolicy::DropClient | SlowConsumerPolicy::Error => {
+                tx.clone().try_reserve_owned().ok()
+            }
+        };
+
+        Self {
+            tx,
+            options,
+            status_permit: Mutex::new(status_permit),
+        }
+    }
+
+    pub async fn send(&self, item: TDest) -> Result<(), StreamSendError> {
+        let result = match self.options.slow_consumer_policy {
+            SlowConsumerPolicy::Wait => self
+                .tx
+                .send(Ok(item))
+                .await
+                .map_err(|_| StreamSendError::Closed),
+            SlowConsumerPolicy::DropClient | SlowConsumerPolicy::Error => self
+                .tx
+                .send_timeout(Ok(item), self.options.send_timeout)
+                .await
+                .map_err(|err| match err {
+                    SendTimeoutError::Timeout(_) => StreamSendError::Timeout,
+                    SendTimeoutError::Closed(_) => StreamSendError::Closed,
+                }),
+        };
+
+        if let Err(err) = result {
+            self.handle_error(err).await;
+            return Err(err);
+        }
+
+        Ok(())
+    }
+
+    /// Returns None once the source is over or the client is disconnected
+    pub async fn get_next<TStream: futures::Stream + Unpin>(
+        &self,
+        src: &mut TStream,
+    ) -> Option<TStream::Item> {
+        let closed = std::pin::pin!(self.tx.closed());
+
+        match futures::future::select(closed, src.next()).await {
+            Either::Left(_) => None,
+            Either::Right((item, _)) => item,
+        }
+    }
+
+    pub fn is_closed(&self) -> bool {
+        self.tx.is_closed()
+    }
+
+    pub async fn send_error(&self, status: tonic::Status) {
+        let status = match self.send_status_to_reserved_slot(status) {
+            Ok(()) => return,
+            Err(status) => status,
+        };
+
+        let result = self
+            .tx
+            .send_timeout(Err(status), self.options.send_timeout)
+            .await;
+
+        if result.is_err() {
+            my_logger::LOGGER.write_warning(
+                "ServerStreamSender::send_error",
+                "Can not send error status to grpc stream",
+                LogEventCtx::new(),
+            );
+        }
+    }
+
+    async fn handle_error(&self, err: StreamSendError) {
+        my_logger::LOGGER.write_warning(
+            "ServerStreamSender::send",
+            format!("Can not send to grpc stream. Err: {:?}", err),
+            LogEventCtx::new()
+                .add("SendTimeout", format!("{:?}", self.options.send_timeout))
+                .add(
+                    "SlowConsumerPolicy",
+                    format!("{:?}", self.options.slow_consumer_policy),
+                ),
+        );
+
+        if let Some(on_error) = self.options.on_error.as_ref() {
+            on_error(err);
+        }
+
+        if err != StreamSendError::Timeout {
+            return;
+        }
+
+        let status = match self.options.slow_consumer_policy {
+            SlowConsumerPolicy::DropClient => tonic::Status::deadline_exceeded(
+                "Stream is dropped since client does not read it fast enough",
+            ),
+            SlowConsumerPolicy::Error => {
+                tonic::Status::resource_exhausted("Client does not read the stream fast enough")
+            }
+            SlowConsumerPolicy::Wait => return,
+        };
+
+        let _ = self.send_status_to_reserved_slot(status);
+    }
+
+    /// Returns the status back once the slot is already used
+    fn send_status_to_reserved_slot(&self, status: tonic::Status) -> Result<(), tonic::Status> {
+        match self.status_permit.lock().unwrap().take() {
+            Some(permit) => {
+                permit.send(Err(status));
+                Ok(())
+            }
+            None => Err(status),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::Duration;
+
+    use super::*;
+
+    #[test]
+    fn test_slow_consumer_gets_error_status() {
+        let rt = tokio::runtime::Builder::new_current_thread()
+            .enable_time()
+            .build()
+            .unwrap();
+
+        rt.block_on(async {
+            let options = ServerStreamOptions::default()
+                .with_channel_size(1)
+                .with_send_timeout(Duration::from_millis(10))
+                .with_slow_consumer_policy(SlowConsumerPolicy::Error);
+
+            let (tx, mut rx) = tokio::sync::mpsc::channel(options.get_channel_capacity());
+
+            let sender = ServerStreamSender::new(tx, options);
+
+            assert!(sender.send(1).await.is_ok());
+            assert_eq!(sender.send(2).await, Err(StreamSendError::Timeout));
+
+            drop(sender);
+
+            assert_eq!(rx.recv().await.unwrap().unwrap(), 1);
+
+            let status = rx.recv().await.unwrap().err().unwrap();
+            assert_eq!(status.code(), tonic::Code::ResourceExhausted);
+
+            assert!(rx.recv().await.is_none());
+        });
+    }
+
+    #[test]
+    fn test_dropped_client_gets_deadline_exceeded_status() {
+        let rt = tokio::runtime::Builder::new_current_thread()
+            .enable_time()
+            .build()
+            .unwrap();
+
+        rt.block_on(async {
+            let options = ServerStreamOptions::default()
+                .with_channel_size(0)
+                .with_send_timeout(Duration::from_millis(10));
+
+            assert_eq!(options.channel_size, 1);
+
+            let (tx, mut rx) = tokio::sync::mpsc::channel(options.get_channel_capacity());
+
+            let sender = ServerStreamSender::new(tx, options);
+
+            assert!(sender.send(1).await.is_ok());
+            assert_eq!(sender.send(2).await, Err(StreamSendError::Timeout));
+
+            drop(sender);
+
+            assert_eq!(rx.recv().await.unwrap().unwrap(), 1);
+
+            let status = rx.recv().await.unwrap().err().unwrap();
+            assert_eq!(status.code(), tonic::Code::DeadlineExceeded);
+
+            assert!(rx.recv().await.is_none());
+        });
+    }
+}
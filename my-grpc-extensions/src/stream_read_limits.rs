@@ -0,0 +1,115 @@
+use crate::GrpcReadError;
+
+/// Caps applied while collecting server stream items into collections. No limits by default
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct StreamReadLimits {
+    pub max_items: Option<usize>,
+    /// Total size of the items calculated as protobuf encoded length
+    pub max_bytes: Option<usize>,
+}
+
+impl StreamReadLimits {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn with_max_items(mut self, max_items: usize) -> Self {
+        self.max_items = Some(max_items);
+        self
+    }
+
+    pub fn with_max_bytes(mut self, max_bytes: usize) -> Self {
+        self.max_bytes = Some(max_bytes);
+        self
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum StreamReadLimit {
+    MaxItems(usize),
+    MaxBytes(usize),
+}
+
+pub(crate) struct StreamReadCounter {
+    limits: StreamReadLimits,
+    items: usize,
+    bytes: usize,
+}
+
+impl StreamReadCounter {
+    pub fn new(limits: StreamReadLimits) -> Self {
+        Self {
+            limits,
+            items: 0,
+            bytes: 0,
+        }
+    }
+
+    /// Checks `max_items` only. Size of the item is not known without `prost::Message`
+    pub fn add_item(&mut self) -> Result<(), GrpcReadError> {
+        self.items += 1;
+
+        if let Some(max_items) = self.limits.max_items {
+            if self.items > max_items {
+                return Err(GrpcReadError::LimitExceeded(StreamReadLimit::MaxItems(
+                    max_items,
+                )));
+            }
+        }
+
+        Ok(())
+    }
+
+    pub fn add<T: prost::Message>(&mut self, item: &T) -> Result<(), GrpcReadError> {
+        self.add_item()?;
+
+        if let Some(max_bytes) = self.limits.max_bytes {
+            self.bytes += item.encoded_len();
+            if self.bytes > max_bytes {
+                return Err(GrpcReadError::LimitExceeded(StreamReadLimit::MaxBytes(
+                    max_bytes,
+                )));
+            }
+        }
+
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_limits() {
+        let mut counter = StreamReadCounter::new(StreamReadLimits::new().with_max_items(2));
+        assert!(counter.add(&"a".to_string()).is_ok());
+        assert!(counter.add(&"b".to_string()).is_ok());
+        let err = counter.add(&"c".to_string()).unwrap_err();
+        assert!(matches!(
+            err,
+            GrpcReadError::LimitExceeded(StreamReadLimit::MaxItems(2))
+        ));
+
+        let mut counter = StreamReadCounter::new(StreamReadLimits::new().with_max_bytes(10));
+        assert!(counter.add(&"12345".to_string()).is_ok());
+        let err = counter.add(&"12345".to_string()).unwrap_err();
+        assert!(matches!(
+            err,
+            GrpcReadError::LimitExceeded(StreamReadLimit::MaxBytes(10))
+        ));
+
+        let mut counter =
+            StreamReadCounter::new(StreamReadLimits::new().with_max_items(1).with_max_bytes(1));
+        assert!(counter.add_item().is_ok());
+        assert!(matches!(
+            counter.add_item().unwrap_err(),
+            GrpcReadError::LimitExceeded(StreamReadLimit::MaxItems(1))
+        ));
+
+        let mut counter = StreamReadCounter::new(StreamReadLimits::new());
+        for _ in 0..1000 {
+            assert!(counter.add(&"12345".to_string()).is_ok());
+        }
+    }
+}
@@ -1,48 +1,81 @@
 use std::{collections::HashSet, hash::Hash, sync::Arc};
 
-use tokio::sync::Mutex;
+use tokio::sync::mpsc::Sender;
 
 use super::*;
 
+pub const DEFAULT_STREAMED_REQUEST_CHANNEL_SIZE: usize = 1024;
+
 pub struct StreamedRequest<TItem: Send + Sync + 'static + Clone> {
     inner: Arc<StreamedRequestInner<TItem>>,
-    channel_size: usize,
+    sender: std::sync::Mutex<Option<Sender<TItem>>>,
+    retry_policy: StreamedRequestRetryPolicy,
 }
 
 impl<TItem: Send + Sync + 'static + Clone> StreamedRequest<TItem> {
     pub fn new_as_vec(data: Vec<TItem>) -> Self {
         Self {
-            inner: Arc::new(StreamedRequestInner::AsVec(data)),
-            channel_size: 1024,
+            inner: Arc::new(StreamedRequestInner::new(StreamedRequestItems::AsVec(data))),
+            sender: std::sync::Mutex::new(None),
+            retry_policy: StreamedRequestRetryPolicy::NoRetry,
         }
     }
 
+    /// Items are sent by the producer. Producer waits once `channel_size` items are not taken by the transport yet
     pub fn new_as_stream() -> Self {
-        let inner = StreamedRequestInner::AsStream(Mutex::new(RequestAsStream::default()));
+        Self::create_as_stream(
+            DEFAULT_STREAMED_REQUEST_CHANNEL_SIZE,
+            StreamedRequestRetryPolicy::NoRetry,
+        )
+    }
+
+    fn create_as_stream(channel_size: usize, retry_policy: StreamedRequestRetryPolicy) -> Self {
+        let (tx, rx) = tokio::sync::mpsc::channel(channel_size);
+        let items = StreamedRequestItems::AsStream(RequestAsStream::new(rx, retry_policy));
         Self {
-            inner: Arc::new(inner),
-            channel_size: 1024,
+            inner: Arc::new(StreamedRequestInner::new(items)),
+            sender: std::sync::Mutex::new(Some(tx)),
+            retry_policy,
         }
     }
 
-    pub fn set_channel_size(mut self, value: usize) -> Self {
-        self.channel_size = value;
-        self
+    /// Has to be set before the producer is taken
+    pub fn set_channel_size(self, value: usize) -> Self {
+        if self.inner.as_stream().is_none() {
+            return self;
+        }
+
+        Self::create_as_stream(value, self.retry_policy)
     }
+
+    /// Has to be set before the producer is taken. Vector based request is always retried
+    pub fn with_retry_policy(self, retry_policy: StreamedRequestRetryPolicy) -> Self {
+        let Some(inner) = self.inner.as_stream() else {
+            return self;
+        };
+
+        Self::create_as_stream(inner.get_channel_size(), retry_policy)
+    }
+
+    /// Stream is finished once the producer is finished or dropped.
+    /// Producer can be taken once - the next ones are closed
     pub fn get_producer(&self) -> StreamedRequestProducer<TItem> {
-        StreamedRequestProducer {
-            inner: self.inner.clone(),
-        }
+        let sender = self.sender.lock().unwrap().take();
+        let eof_delivered = match sender {
+            Some(_) => self.inner.as_stream().map(|inner| inner.subscribe_to_eof()),
+            None => None,
+        };
+
+        StreamedRequestProducer::new(sender, eof_delivered)
     }
 
-    pub fn get_consumer(&self) -> tokio_stream::wrappers::ReceiverStream<TItem> {
-        let (tx, rx) = tokio::sync::mpsc::channel(self.channel_size);
-        let inner = self.inner.clone();
-        tokio::spawn(async move {
-            inner.set_sender(tx).await;
-        });
+    pub fn get_consumer(&self) -> impl futures::Stream<Item = TItem> + Send + 'static {
+        create_consumer_stream(self.inner.clone())
+    }
 
-        tokio_stream::wrappers::ReceiverStream::new(rx)
+    /// Whether the request can be sent again on retry according to the retry policy
+    pub fn is_replayable(&self) -> bool {
+        self.inner.is_replayable()
     }
 }
 
@@ -60,3 +93,82 @@ impl<TItem: Send + Sync + 'static + Clone + Eq + Hash> Into<StreamedRequest<TIte
         StreamedRequest::new_as_vec(data)
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use futures::StreamExt;
+
+    use super::*;
+
+    fn run(future: impl std::future::Future<Output = ()>) {
+        tokio::runtime::Builder::new_current_thread()
+            .build()
+            .unwrap()
+            .block_on(future);
+    }
+
+    #[test]
+    fn test_items_are_streamed_and_finished() {
+        run(async {
+            let request = StreamedRequest::new_as_stream().set_channel_size(2);
+            let producer = request.get_producer();
+
+            let producer_task = tokio::spawn(async move {
+                for i in 0..5 {
+                    producer.send(i).await.unwrap();
+                }
+                producer.finish().await
+            });
+
+            let items: Vec<i32> = request.get_consumer().collect().await;
+            assert_eq!(items, vec![0, 1, 2, 3, 4]);
+            assert_eq!(producer_task.await.unwrap(), Ok(()));
+
+            assert!(request.get_producer().send(5).await.is_err());
+        });
+    }
+
+    #[test]
+    fn test_replay_on_retry() {
+        run(async {
+            let request = StreamedRequest::new_as_stream()
+                .with_retry_policy(StreamedRequestRetryPolicy::Replay { max_items: 2 });
+            let producer = request.get_producer();
+
+            producer.send(1).await.unwrap();
+            producer.send(2).await.unwrap();
+            producer.send(3).await.unwrap();
+            drop(producer);
+
+            let mut first_attempt = Box::pin(request.get_consumer());
+            assert_eq!(first_attempt.next().await, Some(1));
+            assert_eq!(first_attempt.next().await, Some(2));
+            assert!(request.is_replayable());
+
+            let items: Vec<i32> = request.get_consumer().collect().await;
+            assert_eq!(items, vec![1, 2, 3]);
+            assert!(!request.is_replayable());
+
+            assert_eq!(first_attempt.next().await, None);
+        });
+    }
+
+    #[test]
+    fn test_no_retry_once_items_are_sent() {
+        run(async {
+            let request = StreamedRequest::new_as_stream();
+            let producer = request.get_producer();
+            assert!(request.is_replayable());
+
+            producer.send(1).await.unwrap();
+            let mut consumer = Box::pin(request.get_consumer());
+            assert_eq!(consumer.next().await, Some(1));
+            assert!(!request.is_replayable());
+
+            drop(consumer);
+            drop(request);
+            assert_eq!(producer.send(2).await, Err(StreamedRequestError::Closed));
+            assert_eq!(producer.finish().await, Err(StreamedRequestError::Closed));
+        });
+    }
+}
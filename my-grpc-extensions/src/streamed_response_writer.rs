@@ -1,4 +1,14 @@
-use std::{pin::Pin, sync::Arc, time::Duration};
+use std::{
+    pin::Pin,
+    sync::Arc,
+    task::{Context, Poll},
+    time::Duration,
+};
+
+use tokio::sync::mpsc::{error::SendTimeoutError, Sender};
+use tokio_util::sync::{CancellationToken, DropGuard};
+
+use crate::{BatchingProducerOptions, BatchingStreamedResponseProducer, StreamSendError};
 
 #[deprecated(note = "Please use StreamedResultWriter")]
 pub type GrpcOutputStream<TResult> = StreamedResponseWriter<TResult>;
@@ -7,6 +17,7 @@ pub struct StreamedResponseWriter<TResult: Send + Sync + 'static> {
     tx: Arc<tokio::sync::mpsc::Sender<Result<TResult, tonic::Status>>>,
     rx: Option<tokio::sync::mpsc::Receiver<Result<TResult, tonic::Status>>>,
     time_out: Duration,
+    cancellation_token: CancellationToken,
 }
 
 impl<TResult: Send + Sync + 'static> StreamedResponseWriter<TResult> {
@@ -16,6 +27,7 @@ impl<TResult: Send + Sync + 'static> StreamedResponseWriter<TResult> {
             tx: Arc::new(tx),
             rx: Some(rx),
             time_out: Duration::from_secs(10),
+            cancellation_token: CancellationToken::new(),
         }
     }
 
@@ -24,27 +36,56 @@ impl<TResult: Send + Sync + 'static> StreamedResponseWriter<TResult> {
         self
     }
 
-    pub async fn send(&self, itm: TResult) {
-        self.tx
-            .send_timeout(Result::<_, tonic::Status>::Ok(itm), self.time_out)
-            .await
-            .unwrap();
+    pub async fn send(&self, itm: TResult) -> Result<(), StreamSendError> {
+        send_to_channel(&self.tx, Ok(itm), self.time_out).await
+    }
+
+    pub async fn send_error(&self, err: tonic::Status) -> Result<(), StreamSendError> {
+        send_to_channel(&self.tx, Err(err), self.time_out).await
     }
 
-    pub async fn send_error(&self, err: tonic::Status) {
-        self.tx
-            .send_timeout(Result::<_, tonic::Status>::Err(err), self.time_out)
+    pub async fn send_error_with_details(
+        &self,
+        code: tonic::Code,
+        message: impl Into<String>,
+        details: tonic_types::ErrorDetails,
+    ) -> Result<(), StreamSendError> {
+        use tonic_types::StatusExt;
+        self.send_error(tonic::Status::with_error_details(code, message, details))
             .await
-            .unwrap();
+    }
+
+    /// Client is disconnected or the response stream is dropped
+    pub fn is_closed(&self) -> bool {
+        self.tx.is_closed()
+    }
+
+    pub async fn closed(&self) {
+        self.tx.closed().await
+    }
+
+    /// Token is cancelled once the response stream is dropped by the server (client disconnect)
+    pub fn get_cancellation_token(&self) -> CancellationToken {
+        self.cancellation_token.clone()
     }
 
     pub fn get_stream_producer(&self) -> StreamedResponseProducer<TResult> {
         StreamedResponseProducer {
             tx: self.tx.clone(),
             time_out: self.time_out,
+            cancellation_token: self.cancellation_token.clone(),
         }
     }
 
+    /// Producer which sends items of type TItem to the stream as batches mapped to TResult
+    pub fn get_batching_stream_producer<TItem: Send + 'static>(
+        &self,
+        options: BatchingProducerOptions,
+        mapper: impl Fn(Vec<TItem>) -> TResult + Send + Sync + 'static,
+    ) -> BatchingStreamedResponseProducer<TItem> {
+        BatchingStreamedResponseProducer::new(self.get_stream_producer(), options, mapper)
+    }
+
     pub fn get_result(
         mut self,
     ) -> Result<
@@ -71,7 +112,11 @@ impl<TResult: Send + Sync + 'static> StreamedResponseWriter<TResult> {
 
         let rx = rx.unwrap();
 
-        let output_stream = tokio_stream::wrappers::ReceiverStream::new(rx);
+        let output_stream = CancelOnDropStream {
+            inner: tokio_stream::wrappers::ReceiverStream::new(rx),
+            _drop_guard: self.cancellation_token.clone().drop_guard(),
+        };
+
         let response: Pin<
             Box<
                 dyn futures_util::Stream<Item = Result<TResult, tonic::Status>>
@@ -87,26 +132,92 @@ impl<TResult: Send + Sync + 'static> StreamedResponseWriter<TResult> {
 pub struct StreamedResponseProducer<TResult: Send + Sync + 'static> {
     tx: Arc<tokio::sync::mpsc::Sender<Result<TResult, tonic::Status>>>,
     time_out: Duration,
+    cancellation_token: CancellationToken,
 }
 
 impl<TResult: Send + Sync + 'static> StreamedResponseProducer<TResult> {
-    pub async fn send(&self, item: TResult) -> Result<(), String> {
-        let result = self
-            .tx
-            .send_timeout(Result::<_, tonic::Status>::Ok(item), self.time_out)
-            .await;
-
-        if let Err(err) = result {
-            return Err(format!("{:?}", err));
-        }
+    pub async fn send(&self, item: TResult) -> Result<(), StreamSendError> {
+        send_to_channel(&self.tx, Ok(item), self.time_out).await
+    }
 
-        Ok(())
+    pub async fn send_error(&self, err: tonic::Status) -> Result<(), StreamSendError> {
+        send_to_channel(&self.tx, Err(err), self.time_out).await
     }
 
-    pub async fn send_error(&self, err: tonic::Status) {
-        self.tx
-            .send_timeout(Result::<_, tonic::Status>::Err(err), self.time_out)
+    pub async fn send_error_with_details(
+        &self,
+        code: tonic::Code,
+        message: impl Into<String>,
+        details: tonic_types::ErrorDetails,
+    ) -> Result<(), StreamSendError> {
+        use tonic_types::StatusExt;
+        self.send_error(tonic::Status::with_error_details(code, message, details))
             .await
+    }
+
+    pub fn is_closed(&self) -> bool {
+        self.tx.is_closed()
+    }
+
+    pub async fn closed(&self) {
+        self.tx.closed().await
+    }
+
+    pub fn get_cancellation_token(&self) -> CancellationToken {
+        self.cancellation_token.clone()
+    }
+}
+
+async fn send_to_channel<TResult>(
+    tx: &Sender<Result<TResult, tonic::Status>>,
+    item: Result<TResult, tonic::Status>,
+    time_out: Duration,
+) -> Result<(), StreamSendError> {
+    match tx.send_timeout(item, time_out).await {
+        Ok(_) => Ok(()),
+        Err(SendTimeoutError::Timeout(_)) => Err(StreamSendError::Timeout),
+        Err(SendTimeoutError::Closed(_)) => Err(StreamSendError::Closed),
+    }
+}
+
+struct CancelOnDropStream<TResult> {
+    inner: tokio_stream::wrappers::ReceiverStream<Result<TResult, tonic::Status>>,
+    _drop_guard: DropGuard,
+}
+
+impl<TResult> futures_util::Stream for CancelOnDropStream<TResult> {
+    type Item = Result<TResult, tonic::Status>;
+
+    fn poll_next(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<Self::Item>> {
+        Pin::new(&mut self.inner).poll_next(cx)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::StreamSendError;
+
+    use super::StreamedResponseWriter;
+
+    #[test]
+    fn test_send_to_dropped_stream_does_not_panic() {
+        let writer = StreamedResponseWriter::<i32>::new(1);
+        let producer = writer.get_stream_producer();
+        let token = producer.get_cancellation_token();
+
+        let result = writer.get_result().unwrap();
+
+        assert!(!token.is_cancelled());
+        drop(result);
+        assert!(token.is_cancelled());
+        assert!(producer.is_closed());
+
+        let runtime = tokio::runtime::Builder::new_current_thread()
+            .enable_time()
+            .build()
             .unwrap();
+
+        let result = runtime.block_on(producer.send(1));
+        assert_eq!(result, Err(StreamSendError::Closed));
     }
 }
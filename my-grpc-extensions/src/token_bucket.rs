@@ -0,0 +1,47 @@
+use std::time::Duration;
+
+use tokio::time::Instant;
+
+/// Bucket holds one second of tokens and is refilled with the rate continuously
+pub(crate) struct TokenBucket {
+    rate: f64,
+    tokens: f64,
+    refilled: Instant,
+}
+
+impl TokenBucket {
+    pub fn new(tokens_per_second: u32) -> Self {
+        let rate = tokens_per_second.max(1) as f64;
+        Self {
+            rate,
+            tokens: rate,
+            refilled: Instant::now(),
+        }
+    }
+
+    /// Token is taken in advance. Returns the time to wait until the token is refilled
+    pub fn take(&mut self) -> Duration {
+        let now = Instant::now();
+        let elapsed = now.duration_since(self.refilled).as_secs_f64();
+        self.tokens = (self.tokens + elapsed * self.rate).min(self.rate);
+        self.refilled = now;
+
+        self.tokens -= 1.0;
+
+        if self.tokens >= 0.0 {
+            return Duration::ZERO;
+        }
+
+        Duration::from_secs_f64(-self.tokens / self.rate)
+    }
+
+    /// Returns the token taken by `take` once the request is not going to wait for it
+    pub fn give_back(&mut self) {
+        self.tokens += 1.0;
+    }
+
+    pub fn is_full(&self) -> bool {
+        let elapsed = self.refilled.elapsed().as_secs_f64();
+        self.tokens + elapsed * self.rate >= self.rate
+    }
+}
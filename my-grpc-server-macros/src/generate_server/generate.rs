@@ -35,9 +35,47 @@ pub fn generate(input: proc_macro2::TokenStream) -> Result<proc_macro::TokenStre
         None => quote::quote! {super::SdkGrpcService},
     };
 
-    let crate_ns = params_list.get_named_param("crate_ns")?;
-    let crate_ns = crate_ns.unwrap_any_value_as_str()?;
-    let crate_ns = crate_ns.as_str()?;
+    let generate_proto = match params_list.try_get_named_param("generate_proto") {
+        Some(generate_proto) => {
+            let value = generate_proto.unwrap_as_value()?;
+            let value = value.unwrap_value()?;
+            let value = value.as_bool()?;
+            Some((generate_proto, value.get_value()))
+        }
+        None => None,
+    };
+
+    let (crate_ns, proto_module) = match generate_proto {
+        Some((generate_proto, true)) => {
+            let module_name = match params_list.try_get_named_param("proto_module") {
+                Some(proto_module) => {
+                    let proto_module = proto_module.unwrap_any_value_as_str()?;
+                    proto_module.as_str()?.to_string()
+                }
+                None => service_description.get_service_name().as_snake_case(),
+            };
+
+            let proto_module = my_grpc_extensions_build::ProtoCodegen::new(proto_file)
+                .build_server(true)
+                .generate_module(&module_name)
+                .map_err(|err| generate_proto.throw_error_at_value_token(err.as_str()))?;
+
+            let proto_module =
+                proc_macro2::TokenStream::from_str(proto_module.as_str()).map_err(|err| {
+                    generate_proto.throw_error_at_value_token(
+                        format!("Can not parse generated code. Err: {}", err).as_str(),
+                    )
+                })?;
+
+            (format!("self::{}", module_name), proto_module)
+        }
+        _ => {
+            let crate_ns = params_list.get_named_param("crate_ns")?;
+            let crate_ns = crate_ns.unwrap_any_value_as_str()?;
+            (crate_ns.as_str()?.to_string(), quote::quote! {})
+        }
+    };
+    let crate_ns = crate_ns.as_str();
 
     let with_telemetry = params_list.try_get_named_param("with_telemetry");
 
@@ -51,6 +89,63 @@ pub fn generate(input: proc_macro2::TokenStream) -> Result<proc_macro::TokenStre
         None => false,
     };
 
+    let idempotency_ttl_sec: Option<u64> =
+        match params_list.try_get_named_param("idempotency_ttl_sec") {
+            Some(value) => Some(value.try_into()?),
+            None => None,
+        };
+
+    let stream_channel_size: usize = match params_list.try_get_named_param("stream_channel_size") {
+        Some(value) => value.try_into()?,
+        None => 32,
+    };
+
+    let handlers = match params_list.try_get_named_param("handlers") {
+        Some(handlers) => Some(handlers.unwrap_any_value_as_str()?.as_str()?.to_string()),
+        None => None,
+    };
+
+    let app_field = match params_list.try_get_named_param("app_field") {
+        Some(app_field) => app_field.unwrap_any_value_as_str()?.as_str()?.to_string(),
+        None => "app".to_string(),
+    };
+
+    let app_field = proc_macro2::TokenStream::from_str(app_field.as_str()).unwrap();
+
+    let pass_request = match params_list.try_get_named_param("pass_request") {
+        Some(pass_request) => {
+            let value = pass_request.unwrap_as_value()?;
+            let value = value.unwrap_value()?;
+            value.as_bool()?.get_value()
+        }
+        None => false,
+    };
+
+    let returns_result = match params_list.try_get_named_param("returns_result") {
+        Some(returns_result) => {
+            let value = returns_result.unwrap_as_value()?;
+            let value = value.unwrap_value()?;
+            value.as_bool()?.get_value()
+        }
+        None => false,
+    };
+
+    let service_limits = super::server_limits::ServerLimits::read(&params_list)?;
+
+    let overrides = super::handler_override::HandlerOverride::new(&params_list)?;
+
+    for (override_fn_name, fn_override) in &overrides {
+        if !service_description.has_method(override_fn_name) {
+            let message = format!(
+                "Method {} is not found in proto file for service {}",
+                override_fn_name, service_description.service_name
+            );
+            return Err(fn_override
+                .token_stream
+                .throw_error_at_value_token(message.as_str()));
+        }
+    }
+
     let mut functions = Vec::new();
 
     for rpc in service_description.rpc.iter() {
@@ -115,28 +210,155 @@ pub fn generate(input: proc_macro2::TokenStream) -> Result<proc_macro::TokenStre
             )
         };
 
-        if let Some(telemetry_injection) = telemetry_injection {
-            functions.push(quote::quote! {
+        let docs = rpc.comments.iter();
 
-                #stream_description
+        let (telemetry_injection, telemetry_param) = match telemetry_injection {
+            Some(telemetry_injection) => (telemetry_injection, quote::quote! {,my_telemetry}),
+            None => (quote::quote! {}, quote::quote! {}),
+        };
 
-                async fn #fn_name(&self, request:#input_param)->Result<#out_type, tonic::Status>{
-                    #telemetry_injection
-                    let request = request.into_inner();
-                    let result = #fn_name(&self.app, request.into(),my_telemetry).await;
-                    #result_conversion
-                }
-            });
+        let fn_override = overrides.get(&rpc.name);
+
+        let handler = match fn_override.and_then(|itm| itm.handler.as_ref()) {
+            Some(handler) => handler.to_string(),
+            None => match &handlers {
+                Some(handlers) => format!("{}::{}", handlers, fn_name_str.as_snake_case()),
+                None => fn_name_str.as_snake_case(),
+            },
+        };
+
+        let handler = proc_macro2::TokenStream::from_str(handler.as_str()).unwrap();
+
+        let pass_request = match fn_override.and_then(|itm| itm.pass_request) {
+            Some(value) => value,
+            None => pass_request,
+        };
+
+        let returns_result = match fn_override.and_then(|itm| itm.returns_result) {
+            Some(value) => value,
+            None => returns_result,
+        };
+
+        let map_err = if returns_result {
+            quote::quote! {.map_err(my_grpc_extensions::IntoGrpcStatus::into_grpc_status)?}
         } else {
-            functions.push(quote::quote! {
-                #stream_description
-                async fn #fn_name(&self, request:#input_param)->Result<#out_type, tonic::Status>{
-                    let request = request.into_inner();
-                    let result = #fn_name(&self.app, request.into()).await;
-                    #result_conversion
-                }
-            });
+            quote::quote! {}
+        };
+
+        let limiter = match fn_override {
+            Some(fn_override) => service_limits.merge(&fn_override.limits),
+            None => service_limits,
         }
+        .generate_limiter(rpc.name.as_str());
+
+        let is_input_stream = match rpc.get_input_param() {
+            Some(input_param) => input_param.is_stream(),
+            None => false,
+        };
+
+        let is_output_stream = match rpc.get_output_param() {
+            Some(output_param) => output_param.is_stream(),
+            None => false,
+        };
+
+        let (request_prelude, request_arg) = if pass_request {
+            (quote::quote! {}, quote::quote! {request})
+        } else if is_input_stream {
+            (
+                quote::quote! {
+                    let request = my_grpc_extensions::StreamedRequestReader::new(request.into_inner());
+                },
+                quote::quote! {request},
+            )
+        } else {
+            (
+                quote::quote! {let request = request.into_inner();},
+                quote::quote! {request.into()},
+            )
+        };
+
+        let idempotency_ttl_sec = match rpc.get_output_param() {
+            Some(proto_file_reader::ParamType::Single(tp_name)) => {
+                if !is_input_stream && !rpc.get_idempotency_level().is_safe_to_retry() {
+                    idempotency_ttl_sec.map(|ttl_sec| (tp_name, ttl_sec))
+                } else {
+                    None
+                }
+            }
+            _ => None,
+        };
+
+        let body = if let Some((tp_name, ttl_sec)) = idempotency_ttl_sec {
+            let tp_name = proc_macro2::TokenStream::from_str(tp_name).unwrap();
+            quote::quote! {
+                static IDEMPOTENCY_CACHE: std::sync::LazyLock<my_grpc_extensions::IdempotencyCache<#tp_name>> =
+                    std::sync::LazyLock::new(|| my_grpc_extensions::IdempotencyCache::new(std::time::Duration::from_secs(#ttl_sec)));
+
+                let idempotency_key = my_grpc_extensions::IdempotencyCacheKey::from_request(&request);
+                #request_prelude
+                let result = IDEMPOTENCY_CACHE.get_or_try_execute(idempotency_key, async move {
+                    let result: #out_type = #handler(&self.#app_field, #request_arg #telemetry_param).await #map_err .into();
+                    Ok(result.into_inner())
+                }).await?;
+                Ok(tonic::Response::new(result))
+            }
+        } else if is_input_stream && is_output_stream {
+            let (error_producer, handler_call) = if returns_result {
+                (
+                    quote::quote! {let error_producer = result.get_stream_producer();},
+                    quote::quote! {
+                        let result = #handler(&app, #request_arg, producer #telemetry_param).await;
+                        if let Err(err) = result {
+                            let _ = error_producer.send_error(my_grpc_extensions::IntoGrpcStatus::into_grpc_status(err)).await;
+                        }
+                    },
+                )
+            } else {
+                (
+                    quote::quote! {},
+                    quote::quote! {
+                        #handler(&app, #request_arg, producer #telemetry_param).await;
+                    },
+                )
+            };
+
+            quote::quote! {
+                #request_prelude
+                let result = my_grpc_extensions::StreamedResponseWriter::new(#stream_channel_size);
+                let producer = result.get_stream_producer();
+                let app = self.#app_field.clone();
+                #error_producer
+                tokio::spawn(async move {
+                    #handler_call
+                });
+                result.get_result()
+            }
+        } else {
+            quote::quote! {
+                #request_prelude
+                let result = #handler(&self.#app_field, #request_arg #telemetry_param).await #map_err;
+                #result_conversion
+            }
+        };
+
+        let body = if limiter.is_some() && is_output_stream {
+            quote::quote! {
+                let response: Result<#out_type, tonic::Status> = { #body };
+                Ok(response?.map(|stream| _limiter_permit.keep_with_stream(stream)))
+            }
+        } else {
+            body
+        };
+
+        functions.push(quote::quote! {
+            #stream_description
+            #(#[doc = #docs])*
+            async fn #fn_name(&self, request:#input_param)->Result<#out_type, tonic::Status>{
+                #limiter
+                #telemetry_injection
+                #body
+            }
+        });
     }
 
     let service_name_snake_case = service_description.get_service_name().as_snake_case();
@@ -150,6 +372,8 @@ pub fn generate(input: proc_macro2::TokenStream) -> Result<proc_macro::TokenStre
 
     let result = quote::quote! {
 
+        #proto_module
+
         use #server_ns;
         use #crate_ns::*;
 
@@ -0,0 +1,68 @@
+use std::collections::HashMap;
+
+use types_reader::TokensObject;
+
+use super::server_limits::ServerLimits;
+
+pub struct HandlerOverride<'s> {
+    pub handler: Option<String>,
+    pub pass_request: Option<bool>,
+    pub returns_result: Option<bool>,
+    pub limits: ServerLimits,
+    pub token_stream: &'s TokensObject,
+}
+
+impl<'s> HandlerOverride<'s> {
+    pub fn new(params: &'s TokensObject) -> Result<HashMap<String, Self>, syn::Error> {
+        let overrides = params.try_get_named_param("overrides");
+
+        if overrides.is_none() {
+            return Ok(HashMap::new());
+        }
+
+        let overrides = overrides.unwrap().unwrap_as_vec()?;
+
+        let mut result = HashMap::new();
+
+        for item in overrides.iter() {
+            let name: String = item.get_named_param("fn_name")?.try_into()?;
+
+            let handler = match item.try_get_named_param("handler") {
+                Some(handler) => Some(handler.unwrap_any_value_as_str()?.as_str()?.to_string()),
+                None => None,
+            };
+
+            let pass_request = read_bool_param(item, "pass_request")?;
+            let returns_result = read_bool_param(item, "returns_result")?;
+            let limits = ServerLimits::read(item)?;
+
+            result.insert(
+                name,
+                HandlerOverride {
+                    handler,
+                    pass_request,
+                    returns_result,
+                    limits,
+                    token_stream: item,
+                },
+            );
+        }
+
+        if result.len() == 0 {
+            return Err(params.throw_error_at_param_token("Overrides list can not be empty. Just remove field if you do not want to override any function"));
+        }
+
+        Ok(result)
+    }
+}
+
+fn read_bool_param(item: &TokensObject, param_name: &str) -> Result<Option<bool>, syn::Error> {
+    match item.try_get_named_param(param_name) {
+        Some(param) => {
+            let value = param.unwrap_as_value()?;
+            let value = value.unwrap_value()?;
+            Ok(Some(value.as_bool()?.get_value()))
+        }
+        None => Ok(None),
+    }
+}
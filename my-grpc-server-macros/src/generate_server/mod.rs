@@ -1,2 +1,4 @@
 mod generate;
 pub use generate::*;
+mod handler_override;
+mod server_limits;
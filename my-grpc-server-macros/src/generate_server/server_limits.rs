@@ -0,0 +1,104 @@
+use types_reader::TokensObject;
+
+#[derive(Default, Clone, Copy)]
+pub struct ServerLimits {
+    pub max_concurrent: Option<usize>,
+    pub max_requests_per_second: Option<u32>,
+    pub max_concurrent_per_ip: Option<usize>,
+    pub max_requests_per_second_per_ip: Option<u32>,
+}
+
+impl ServerLimits {
+    pub fn read(params: &TokensObject) -> Result<Self, syn::Error> {
+        let max_concurrent: Option<usize> = match params.try_get_named_param("max_concurrent") {
+            Some(value) => Some(value.try_into()?),
+            None => None,
+        };
+
+        let max_requests_per_second: Option<u32> =
+            match params.try_get_named_param("max_requests_per_second") {
+                Some(value) => Some(value.try_into()?),
+                None => None,
+            };
+
+        let max_concurrent_per_ip: Option<usize> =
+            match params.try_get_named_param("max_concurrent_per_ip") {
+                Some(value) => Some(value.try_into()?),
+                None => None,
+            };
+
+        let max_requests_per_second_per_ip: Option<u32> =
+            match params.try_get_named_param("max_requests_per_second_per_ip") {
+                Some(value) => Some(value.try_into()?),
+                None => None,
+            };
+
+        Ok(Self {
+            max_concurrent,
+            max_requests_per_second,
+            max_concurrent_per_ip,
+            max_requests_per_second_per_ip,
+        })
+    }
+
+    /// Limits set for the rpc take precedence over the limits of the service
+    pub fn merge(&self, rpc_limits: &ServerLimits) -> Self {
+        Self {
+            max_concurrent: rpc_limits.max_concurrent.or(self.max_concurrent),
+            max_requests_per_second: rpc_limits
+                .max_requests_per_second
+                .or(self.max_requests_per_second),
+            max_concurrent_per_ip: rpc_limits
+                .max_concurrent_per_ip
+                .or(self.max_concurrent_per_ip),
+            max_requests_per_second_per_ip: rpc_limits
+                .max_requests_per_second_per_ip
+                .or(self.max_requests_per_second_per_ip),
+        }
+    }
+
+    /// Limiter is checked before the handler is called. `None` if there are no limits.
+    /// Permit of streaming rpcs is moved into the response stream
+    pub fn generate_limiter(&self, rpc_name: &str) -> Option<proc_macro2::TokenStream> {
+        let with_limits = generate_limits(self.max_concurrent, self.max_requests_per_second)
+            .map(|limits| quote::quote!(.with_limits(#limits)));
+
+        let with_per_ip_limits = generate_limits(
+            self.max_concurrent_per_ip,
+            self.max_requests_per_second_per_ip,
+        )
+        .map(|limits| quote::quote!(.with_per_ip_limits(#limits)));
+
+        if with_limits.is_none() && with_per_ip_limits.is_none() {
+            return None;
+        }
+
+        Some(quote::quote! {
+            static LIMITER: std::sync::LazyLock<my_grpc_extensions::GrpcServerLimiter> =
+                std::sync::LazyLock::new(|| my_grpc_extensions::GrpcServerLimiter::new(#rpc_name) #with_limits #with_per_ip_limits);
+
+            let _limiter_permit = LIMITER.try_acquire(request.remote_addr())?;
+        })
+    }
+}
+
+fn generate_limits(
+    max_concurrent: Option<usize>,
+    max_requests_per_second: Option<u32>,
+) -> Option<proc_macro2::TokenStream> {
+    if max_concurrent.is_none() && max_requests_per_second.is_none() {
+        return None;
+    }
+
+    let with_max_concurrent =
+        max_concurrent.map(|value| quote::quote!(.with_max_concurrent(#value)));
+
+    let with_max_requests_per_second =
+        max_requests_per_second.map(|value| quote::quote!(.with_max_requests_per_second(#value)));
+
+    Some(quote::quote! {
+        my_grpc_extensions::GrpcServerLimits::new()
+            #with_max_concurrent
+            #with_max_requests_per_second
+    })
+}
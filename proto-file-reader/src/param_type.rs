@@ -27,10 +27,15 @@ impl<'s> ParamType<'s> {
             return Self::Single("()").into();
         }
 
+        let name = match get_well_known_type(name) {
+            Some(rust_type) => rust_type,
+            None => name.split('.').last().unwrap(),
+        };
+
         if is_vec {
-            Self::Stream(name.split('.').last().unwrap()).into()
+            Self::Stream(name).into()
         } else {
-            Self::Single(name.split('.').last().unwrap()).into()
+            Self::Single(name).into()
         }
     }
 
@@ -62,3 +67,55 @@ impl<'s> ParamType<'s> {
         }
     }
 }
+
+/// Same mapping of google.protobuf types as prost-build uses for generated code
+fn get_well_known_type(name: &str) -> Option<&'static str> {
+    let name = name.strip_prefix("google.protobuf.")?;
+
+    let result = match name {
+        "StringValue" => "::prost::alloc::string::String",
+        "BoolValue" => "bool",
+        "Int32Value" => "i32",
+        "Int64Value" => "i64",
+        "UInt32Value" => "u32",
+        "UInt64Value" => "u64",
+        "DoubleValue" => "f64",
+        "FloatValue" => "f32",
+        "BytesValue" => "::prost::alloc::vec::Vec<u8>",
+        "Any" => "::prost_types::Any",
+        "Duration" => "::prost_types::Duration",
+        "FieldMask" => "::prost_types::FieldMask",
+        "ListValue" => "::prost_types::ListValue",
+        "Struct" => "::prost_types::Struct",
+        "Timestamp" => "::prost_types::Timestamp",
+        "Value" => "::prost_types::Value",
+        _ => return None,
+    };
+
+    Some(result)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::ParamType;
+
+    #[test]
+    fn test_well_known_types() {
+        let param = ParamType::parse("google.protobuf.Empty").unwrap();
+        assert_eq!(param.get_name(), "()");
+
+        let param = ParamType::parse("google.protobuf.StringValue").unwrap();
+        assert_eq!(param.get_name(), "::prost::alloc::string::String");
+        assert!(!param.is_stream());
+
+        let param = ParamType::parse("stream google.protobuf.Int64Value").unwrap();
+        assert_eq!(param.get_name(), "i64");
+        assert!(param.is_stream());
+
+        let param = ParamType::parse("google.protobuf.Timestamp").unwrap();
+        assert_eq!(param.get_name(), "::prost_types::Timestamp");
+
+        let param = ParamType::parse("keyvalue.GetRequest").unwrap();
+        assert_eq!(param.get_name(), "GetRequest");
+    }
+}
@@ -1,12 +1,17 @@
 use std::io::{BufRead, BufReader};
 
-use super::{ParamType, proto_tokens_reader::ProtoTokensReader};
+use super::{
+    ParamType,
+    proto_tokens_reader::{ProtoTokensReader, remove_block_comments, split_comment},
+};
 
 #[derive(Debug)]
 pub struct ProtoRpc {
     pub name: String,
     input_param: String,
     output_param: String,
+    pub comments: Vec<String>,
+    pub options: Vec<ProtoOption>,
 }
 
 impl ProtoRpc {
@@ -21,12 +26,123 @@ impl ProtoRpc {
     pub fn get_output_param<'s>(&'s self) -> Option<ParamType<'s>> {
         ParamType::parse(&self.output_param)
     }
+
+    pub fn get_option(&self, name: &str) -> Option<&str> {
+        let option = self.options.iter().find(|itm| itm.name == name)?;
+        Some(option.value.as_str())
+    }
+
+    /// Custom option is matched by the last segment of its name: `(my_grpc.retries)` => `retries`
+    pub fn get_custom_option(&self, name: &str) -> Option<&str> {
+        let option = self
+            .options
+            .iter()
+            .find(|itm| itm.get_custom_option_name() == Some(name))?;
+        Some(option.value.as_str())
+    }
+
+    pub fn is_deprecated(&self) -> bool {
+        self.get_option("deprecated") == Some("true")
+    }
+
+    /// Single request and stream response
+    pub fn is_server_streaming(&self) -> bool {
+        let input_is_stream = match self.get_input_param() {
+            Some(input_param) => input_param.is_stream(),
+            None => false,
+        };
+
+        let output_is_stream = match self.get_output_param() {
+            Some(output_param) => output_param.is_stream(),
+            None => false,
+        };
+
+        !input_is_stream && output_is_stream
+    }
+
+    /// Single request and single response
+    pub fn is_unary(&self) -> bool {
+        let input_is_stream = match self.get_input_param() {
+            Some(input_param) => input_param.is_stream(),
+            None => false,
+        };
+
+        let output_is_stream = match self.get_output_param() {
+            Some(output_param) => output_param.is_stream(),
+            None => false,
+        };
+
+        !input_is_stream && !output_is_stream
+    }
+
+    pub fn get_idempotency_level(&self) -> IdempotencyLevel {
+        match self.get_option("idempotency_level") {
+            Some("NO_SIDE_EFFECTS") => IdempotencyLevel::NoSideEffects,
+            Some("IDEMPOTENT") => IdempotencyLevel::Idempotent,
+            _ => IdempotencyLevel::IdempotencyUnknown,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum IdempotencyLevel {
+    IdempotencyUnknown,
+    NoSideEffects,
+    Idempotent,
+}
+
+impl IdempotencyLevel {
+    pub fn is_safe_to_retry(&self) -> bool {
+        match self {
+            IdempotencyLevel::IdempotencyUnknown => false,
+            IdempotencyLevel::NoSideEffects => true,
+            IdempotencyLevel::Idempotent => true,
+        }
+    }
+}
+
+#[derive(Debug)]
+pub struct ProtoOption {
+    pub name: String,
+    pub value: String,
+}
+
+impl ProtoOption {
+    fn parse(tokens: &[String]) -> Option<Self> {
+        if tokens.first()?.as_str() != "option" {
+            return None;
+        }
+
+        let eq_index = tokens.iter().position(|itm| itm == "=")?;
+
+        let name: String = tokens[1..eq_index].concat();
+
+        let value = tokens[eq_index + 1..].join(" ");
+
+        Some(Self {
+            name,
+            value: value.trim_matches('"').to_string(),
+        })
+    }
+
+    pub fn get_custom_option_name(&self) -> Option<&str> {
+        let name = self.name.strip_prefix('(')?.strip_suffix(')')?;
+        name.rsplit('.').next()
+    }
+}
+
+#[derive(Debug)]
+pub struct ProtoMessage {
+    pub name: String,
+    pub comments: Vec<String>,
 }
 
 #[derive(Debug)]
 pub struct ProtoServiceDescription {
     pub service_name: String,
     pub rpc: Vec<ProtoRpc>,
+    pub comments: Vec<String>,
+    pub messages: Vec<ProtoMessage>,
 }
 
 impl ProtoServiceDescription {
@@ -34,6 +150,10 @@ impl ProtoServiceDescription {
         ProtoString::new(self.service_name.as_str())
     }
 
+    pub fn get_rpc(&self, method_name: &str) -> Option<&ProtoRpc> {
+        self.rpc.iter().find(|rpc| rpc.name == method_name)
+    }
+
     pub fn has_method(&self, method_name: &str) -> bool {
         for rpc in &self.rpc {
             if rpc.name == method_name {
@@ -44,6 +164,10 @@ impl ProtoServiceDescription {
         false
     }
 
+    pub fn get_message(&self, name: &str) -> Option<&ProtoMessage> {
+        self.messages.iter().find(|itm| itm.name == name)
+    }
+
     pub fn read_proto_file(file_name: &str) -> Self {
         let file = std::fs::File::open(file_name);
 
@@ -55,34 +179,89 @@ impl ProtoServiceDescription {
 
         let reader = BufReader::new(file);
 
+        let lines: Vec<String> = reader.lines().map(|line| line.unwrap()).collect();
+
+        Self::parse_lines(file_name, lines.iter().map(|line| line.as_str()))
+    }
+
+    pub fn from_proto_content(file_name: &str, content: &str) -> Self {
+        Self::parse_lines(file_name, content.lines())
+    }
+
+    fn parse_lines<'s>(file_name: &str, lines: impl Iterator<Item = &'s str>) -> Self {
         let mut service_name = None;
 
+        let mut service_comments = Vec::new();
+
         let mut current_token = CurrentToken::None;
 
         let mut rpc_name = None;
 
+        let mut rpc_comments = Vec::new();
+
         let mut input_param_name = String::new();
 
         let mut out_param_name = String::new();
 
+        let mut rpc_options = Vec::new();
+
+        let mut option_tokens = Vec::new();
+
+        // Depth of `{ }` of aggregate option values like `option (google.api.http) = { get: "/x" };`
+        let mut option_depth = 0;
+
         let mut rpc = Vec::new();
 
-        for line in reader.lines() {
-            let line = line.unwrap();
+        let mut messages = Vec::new();
+
+        let mut leading_comments = Vec::new();
+
+        let mut in_block_comment = false;
+
+        // Keywords are recognized only at the start of a statement, so fields like `string message = 1;` are skipped
+        let mut statement_start = true;
+
+        for line in lines {
+            let (line_code, block_comment) = remove_block_comments(line, &mut in_block_comment);
+
+            let (code, comment) = split_comment(line_code.as_str());
+
+            let comment = comment.or(block_comment.as_deref());
+
+            if code.trim().is_empty() {
+                match comment {
+                    Some(comment) => leading_comments.push(comment.to_string()),
+                    // lines of the block comment without text
+                    None if !line.trim().is_empty() => {}
+                    None => leading_comments.clear(),
+                }
+                continue;
+            }
+
+            let mut element_started_on_line = None;
 
-            for token in ProtoTokensReader::new(line.as_str()) {
+            for token in ProtoTokensReader::new(code) {
                 match current_token {
                     CurrentToken::None => {
-                        if token == "service" {
-                            current_token = CurrentToken::Service;
-                        }
+                        if statement_start {
+                            if token == "service" {
+                                current_token = CurrentToken::Service;
+                            }
 
-                        if token == "rpc" {
-                            current_token = CurrentToken::Rpc;
+                            if token == "rpc" {
+                                current_token = CurrentToken::Rpc;
+                            }
+
+                            if token == "message" {
+                                current_token = CurrentToken::Message;
+                            }
                         }
+
+                        statement_start = token == ";" || token == "{" || token == "}";
                     }
                     CurrentToken::Rpc => {
                         rpc_name = Some(token.to_string());
+                        rpc_comments = std::mem::take(&mut leading_comments);
 
                         input_param_name.clear();
                         out_param_name.clear();
@@ -118,21 +297,31 @@ impl ProtoServiceDescription {
                             continue;
                         }
 
+                        if token == "{" {
+                            rpc_options.clear();
+                            option_tokens.clear();
+                            option_depth = 0;
+                            current_token = CurrentToken::RpcOptions;
+                            continue;
+                        }
+
                         if token == ";" {
-                            if rpc_name.is_none() {
-                                panic!("Somehow rpc_name is null");
+                            if let Some(comment) = comment {
+                                rpc_comments.push(comment.to_string());
                             }
 
-                            let name = rpc_name.as_ref().unwrap();
+                            push_rpc(
+                                &mut rpc,
+                                rpc_name.as_ref(),
+                                &input_param_name,
+                                &out_param_name,
+                                std::mem::take(&mut rpc_comments),
+                                Vec::new(),
+                            );
 
-                            if name != "Ping" {
-                                rpc.push(ProtoRpc {
-                                    name: name.to_string(),
-                                    input_param: input_param_name.to_string(),
-                                    output_param: out_param_name.to_string(),
-                                });
-                            }
                             current_token = CurrentToken::None;
+                            statement_start = true;
+                            continue;
                         }
 
                         if out_param_name.len() > 0 {
@@ -140,12 +329,76 @@ impl ProtoServiceDescription {
                         }
                         out_param_name.push_str(token);
                     }
+                    CurrentToken::RpcOptions => {
+                        if token == "{" {
+                            option_depth += 1;
+                        }
+
+                        if token == "}" && option_depth > 0 {
+                            option_depth -= 1;
+                            option_tokens.push(token.to_string());
+                            continue;
+                        }
+
+                        if token == "}" {
+                            if let Some(comment) = comment {
+                                rpc_comments.push(comment.to_string());
+                            }
+
+                            push_rpc(
+                                &mut rpc,
+                                rpc_name.as_ref(),
+                                &input_param_name,
+                                &out_param_name,
+                                std::mem::take(&mut rpc_comments),
+                                std::mem::take(&mut rpc_options),
+                            );
+
+                            current_token = CurrentToken::None;
+                            statement_start = true;
+                            continue;
+                        }
+
+                        if token == ";" && option_depth == 0 {
+                            if let Some(option) = ProtoOption::parse(&option_tokens) {
+                                rpc_options.push(option);
+                            }
+                            option_tokens.clear();
+                            continue;
+                        }
+
+                        option_tokens.push(token.to_string());
+                    }
                     CurrentToken::Service => {
                         service_name = Some(format!("{}", token));
+                        service_comments = std::mem::take(&mut leading_comments);
+                        element_started_on_line = Some(ElementOnLine::Service);
+                        current_token = CurrentToken::None;
+                    }
+                    CurrentToken::Message => {
+                        messages.push(ProtoMessage {
+                            name: token.to_string(),
+                            comments: std::mem::take(&mut leading_comments),
+                        });
+                        element_started_on_line = Some(ElementOnLine::Message);
                         current_token = CurrentToken::None;
                     }
                 }
             }
+
+            if let Some(comment) = comment {
+                match element_started_on_line {
+                    Some(ElementOnLine::Service) => service_comments.push(comment.to_string()),
+                    Some(ElementOnLine::Message) => {
+                        if let Some(message) = messages.last_mut() {
+                            message.comments.push(comment.to_string());
+                        }
+                    }
+                    None => {}
+                }
+            }
+
+            leading_comments.clear();
         }
 
         if service_name.is_none() {
@@ -155,16 +408,50 @@ impl ProtoServiceDescription {
         Self {
             service_name: service_name.unwrap().to_string(),
             rpc,
+            comments: service_comments,
+            messages,
         }
     }
 }
 
+fn push_rpc(
+    rpc: &mut Vec<ProtoRpc>,
+    rpc_name: Option<&String>,
+    input_param_name: &str,
+    out_param_name: &str,
+    comments: Vec<String>,
+    options: Vec<ProtoOption>,
+) {
+    if rpc_name.is_none() {
+        panic!("Somehow rpc_name is null");
+    }
+
+    let name = rpc_name.unwrap();
+
+    if name != "Ping" {
+        rpc.push(ProtoRpc {
+            name: name.to_string(),
+            input_param: input_param_name.to_string(),
+            output_param: out_param_name.to_string(),
+            comments,
+            options,
+        });
+    }
+}
+
+enum ElementOnLine {
+    Service,
+    Message,
+}
+
 pub enum CurrentToken {
     None,
     Service,
     Rpc,
     RpcExpectingInputParameter,
     RpcExpectingOutputParameter,
+    RpcOptions,
+    Message,
 }
 
 pub fn into_snake_case(src: &str) -> String {
@@ -203,8 +490,128 @@ impl<'s> ProtoString<'s> {
 #[cfg(test)]
 mod tests {
 
+    use super::ProtoServiceDescription;
+
     #[test]
     fn test_into_camel_case() {
         assert_eq!(super::into_snake_case("HelloWorld"), "hello_world");
     }
+
+    #[test]
+    fn test_comments() {
+        let src = r#"
+syntax = "proto3";
+package test;
+
+// Test service
+service TestService { // trailing service comment
+
+    // Reads item
+    // by id
+    rpc Get(GetRequest) returns (GetResponse); // trailing rpc comment
+
+    // detached comment
+
+    rpc Delete(GetRequest) returns (GetResponse);
+}
+
+// Request message
+message GetRequest {
+    // field comment
+    string id = 1;
+}
+"#;
+
+        let result = ProtoServiceDescription::from_proto_content("test.proto", src);
+
+        assert_eq!(
+            result.comments,
+            vec![" Test service", " trailing service comment"]
+        );
+
+        assert_eq!(
+            result.rpc[0].comments,
+            vec![" Reads item", " by id", " trailing rpc comment"]
+        );
+
+        assert_eq!(result.rpc[1].comments.len(), 0);
+
+        let message = result.get_message("GetRequest").unwrap();
+        assert_eq!(message.comments, vec![" Request message"]);
+    }
+
+    #[test]
+    fn test_message_fields_and_block_comments() {
+        let src = r#"
+/**
+ * Test service
+ */
+service TestService {
+    /* Reads item */
+    rpc Get(GetRequest) returns (GetResponse);
+}
+
+message GetResponse {
+    string message = 1;
+    /* rpc Commented(GetRequest) returns (GetResponse); */
+    Message inner = 2;
+}
+"#;
+
+        let result = ProtoServiceDescription::from_proto_content("test.proto", src);
+
+        assert_eq!(result.comments, vec![" Test service"]);
+
+        assert_eq!(result.rpc.len(), 1);
+        assert_eq!(result.rpc[0].comments, vec![" Reads item"]);
+
+        assert_eq!(result.messages.len(), 1);
+        assert_eq!(result.messages[0].name, "GetResponse");
+    }
+
+    #[test]
+    fn test_rpc_options() {
+        let src = r#"
+service TestService {
+    rpc Get(GetRequest) returns (GetResponse) {
+        option deprecated = true;
+        option idempotency_level = NO_SIDE_EFFECTS;
+        option (google.api.http) = {
+            get: "/v1/items";
+        };
+        option (my_grpc.request_timeout_ms) = 500;
+    }
+
+    rpc Delete(GetRequest) returns (stream GetResponse) {}
+
+    rpc Update(GetRequest) returns (GetResponse);
+}
+"#;
+
+        let result = ProtoServiceDescription::from_proto_content("test.proto", src);
+
+        assert_eq!(result.rpc.len(), 3);
+
+        let get = &result.rpc[0];
+        assert!(get.is_deprecated());
+        assert_eq!(
+            get.get_idempotency_level(),
+            super::IdempotencyLevel::NoSideEffects
+        );
+        assert_eq!(get.get_custom_option("request_timeout_ms"), Some("500"));
+        assert_eq!(
+            get.get_custom_option("http"),
+            Some("{ get: \"/v1/items\" ; }")
+        );
+        assert_eq!(get.get_output_param().unwrap().get_name(), "GetResponse");
+
+        let delete = &result.rpc[1];
+        assert!(!delete.is_deprecated());
+        assert!(!delete.get_idempotency_level().is_safe_to_retry());
+        assert!(delete.get_output_param().unwrap().is_stream());
+        assert!(delete.is_server_streaming());
+        assert!(!get.is_server_streaming());
+
+        assert_eq!(result.rpc[2].name, "Update");
+    }
 }
@@ -39,7 +39,7 @@ fn iterate_through_line(line: &[u8], mut pos: usize) -> Option<(&str, usize)> {
             continue;
         }
 
-        if b == b'(' || b == b')' || b == b';' || b == b'{' || b == b'}' {
+        if b == b'(' || b == b')' || b == b';' || b == b'{' || b == b'}' || b == b'=' {
             if let Some(start_pos) = start_pos {
                 let result = std::str::from_utf8(&line[start_pos..pos]).unwrap();
                 return Some((result, pos));
@@ -78,15 +78,101 @@ impl<'s> Iterator for ProtoTokensReader<'s> {
 fn clean_up_comments(src: &str) -> Vec<&[u8]> {
     let mut result = Vec::new();
     for itm in src.split('\n') {
-        if itm.trim().starts_with("//") {
+        let (code, _) = split_comment(itm);
+        if code.trim().len() == 0 {
             continue;
         }
-        result.push(itm.as_bytes());
+        result.push(code.as_bytes());
     }
 
     result
 }
 
+/// Splits line into code part and `//` comment part (without slashes).
+pub fn split_comment(line: &str) -> (&str, Option<&str>) {
+    let bytes = line.as_bytes();
+    let mut in_string = false;
+
+    let mut pos = 0;
+    while pos < bytes.len() {
+        let b = bytes[pos];
+
+        if b == b'"' {
+            in_string = !in_string;
+        }
+
+        if !in_string && b == b'/' && pos + 1 < bytes.len() && bytes[pos + 1] == b'/' {
+            let comment = line[pos..].trim_start_matches('/').trim_end();
+            return (&line[..pos], Some(comment));
+        }
+
+        pos += 1;
+    }
+
+    (line, None)
+}
+
+/// Removes `/* */` comments from the line, `in_block_comment` keeps the state between lines.
+/// Returns the rest of the line and the text of the removed comments.
+pub fn remove_block_comments(line: &str, in_block_comment: &mut bool) -> (String, Option<String>) {
+    let mut code = String::new();
+    let mut comment = String::new();
+    let mut in_string = false;
+
+    let mut rest = line;
+
+    while !rest.is_empty() {
+        if *in_block_comment {
+            let (text, after) = match rest.find("*/") {
+                Some(index) => {
+                    *in_block_comment = false;
+                    (&rest[..index], &rest[index + 2..])
+                }
+                None => (rest, ""),
+            };
+
+            let text = text.trim();
+            let text = text.strip_prefix('*').unwrap_or(text).trim_end();
+
+            if !text.is_empty() {
+                if !text.starts_with(' ') {
+                    comment.push(' ');
+                }
+                comment.push_str(text);
+            }
+
+            rest = after;
+            continue;
+        }
+
+        if !in_string && rest.starts_with("//") {
+            code.push_str(rest);
+            break;
+        }
+
+        if !in_string && rest.starts_with("/*") {
+            *in_block_comment = true;
+            rest = &rest[2..];
+            continue;
+        }
+
+        let ch = rest.chars().next().unwrap();
+
+        if ch == '"' {
+            in_string = !in_string;
+        }
+
+        code.push(ch);
+        rest = &rest[ch.len_utf8()..];
+    }
+
+    if comment.is_empty() {
+        return (code, None);
+    }
+
+    (code, Some(comment))
+}
+
 #[cfg(test)]
 mod tests {
     use super::ProtoTokensReader;
@@ -133,4 +219,55 @@ mod tests {
         pos += 1;
         assert_eq!(result[pos], "}");
     }
+
+    #[test]
+    fn test_comments_are_skipped() {
+        let src = "// Service comment\nservice Test { // trailing\n}";
+
+        let result = ProtoTokensReader::new(src).collect::<Vec<_>>();
+
+        assert_eq!(result, vec!["service", "Test", "{", "}"]);
+    }
+
+    #[test]
+    fn test_remove_block_comments() {
+        let mut in_block_comment = false;
+
+        assert_eq!(
+            super::remove_block_comments("/** Reads item", &mut in_block_comment),
+            (String::new(), Some(" Reads item".to_string()))
+        );
+        assert!(in_block_comment);
+
+        assert_eq!(
+            super::remove_block_comments("  * by id */ rpc Get", &mut in_block_comment),
+            (" rpc Get".to_string(), Some(" by id".to_string()))
+        );
+        assert!(!in_block_comment);
+
+        assert_eq!(
+            super::remove_block_comments(
+                "option a = \"/*\"; /* comment */ // line /* comment",
+                &mut in_block_comment
+            ),
+            (
+                "option a = \"/*\";  // line /* comment".to_string(),
+                Some(" comment".to_string())
+            )
+        );
+        assert!(!in_block_comment);
+    }
+
+    #[test]
+    fn test_split_comment() {
+        assert_eq!(
+            super::split_comment("rpc Get(Req) returns (Res); // Gets value"),
+            ("rpc Get(Req) returns (Res); ", Some(" Gets value"))
+        );
+
+        assert_eq!(
+            super::split_comment("option go_package = \"http://test\";"),
+            ("option go_package = \"http://test\";", None)
+        );
+    }
 }